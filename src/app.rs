@@ -0,0 +1,849 @@
+use std::rc::Rc;
+
+use crate::components::*;
+use crate::color::Color;
+use crate::core::decode::{DecodedQr, VerifyError};
+use crate::core::fountain::DEFAULT_REDUNDANCY_PERCENT;
+use crate::core::gs1::{Gs1Data, Gs1Format};
+use crate::core::segment::Segment;
+use crate::core::{encode_gif, AztecFormat, Charset, DataMatrixShape, EcLevel, KanjiSavings, GeneratedQr, ImageFormat, QrOptions, QrRequest, DEFAULT_FRAME_DELAY_MS, DEFAULT_MODULE_SIZE, MAX_EC_PERCENT, MAX_PDF417_COLUMNS, MAX_QUIET_ZONE, MAX_SECURITY_LEVEL, MAX_SYMBOLS, MIN_EC_PERCENT, RMQR_HEIGHTS, RMQR_WIDTHS, Symbology, mask_count};
+use yew::prelude::*;
+use base64::{Engine as _, engine::general_purpose};
+use web_sys::{HtmlAnchorElement, HtmlInputElement, HtmlSelectElement};
+
+#[derive(Properties, PartialEq)]
+struct OptionsProps {
+    options: QrOptions,
+    request: Option<QrRequest>,
+    codes: Vec<GeneratedQr>,
+    dispatch: Callback<AppAction>
+}
+
+fn kanji_savings_text(savings: KanjiSavings) -> String {
+    if let Some(character) = savings.unsupported {
+        return format!("'{character}' is not in Shift-JIS, so the text was kept as UTF-8 bytes.");
+    }
+    match savings.saved_bits() {
+        saved if saved > 0 => format!("{} bits instead of {} as UTF-8, {saved} bits (about {} bytes) saved.", savings.bits, savings.utf8_bits, saved / 8),
+        0 => String::from("Same size as UTF-8 byte mode."),
+        saved => format!("{} bits instead of {} as UTF-8, {} bits larger.", savings.bits, savings.utf8_bits, -saved),
+    }
+}
+
+#[function_component(Options)]
+fn options(props: &OptionsProps) -> Html {
+    let options_visible = use_state(|| false);
+
+    let change_ec = {
+        let dispatch = props.dispatch.clone();
+        move |level| {
+            let dispatch = dispatch.clone();
+            move |_| {
+                dispatch.emit(AppAction::UpdateEcLevel(level));
+            }
+        }
+    };
+
+    // keeps the range valid by dragging the other end along
+    let change_version = {
+        let dispatch = props.dispatch.clone();
+        let QrOptions { symbology, min_version, max_version, .. } = props.options;
+        move |is_min: bool| {
+            let dispatch = dispatch.clone();
+            move |e: Event| {
+                let input = e.target_unchecked_into::<HtmlInputElement>();
+                if let Ok(version) = input.value().parse::<i16>() {
+                    let version = version.clamp(symbology.min_version(), symbology.max_version());
+                    let range = if is_min {
+                        (version, max_version.max(version))
+                    } else {
+                        (min_version.min(version), version)
+                    };
+                    dispatch.emit(AppAction::UpdateVersionRange(range.0, range.1));
+                }
+            }
+        }
+    };
+
+    let change_mask = {
+        let dispatch = props.dispatch.clone();
+        move |mask| {
+            let dispatch = dispatch.clone();
+            move |_| {
+                dispatch.emit(AppAction::UpdateMask(mask));
+            }
+        }
+    };
+
+    let change_symbology = {
+        let dispatch = props.dispatch.clone();
+        move |symbology| {
+            let dispatch = dispatch.clone();
+            move |_| {
+                dispatch.emit(AppAction::UpdateSymbology(symbology));
+            }
+        }
+    };
+
+    let change_shape = {
+        let dispatch = props.dispatch.clone();
+        move |shape| {
+            let dispatch = dispatch.clone();
+            move |_| {
+                dispatch.emit(AppAction::UpdateDataMatrixShape(shape));
+            }
+        }
+    };
+
+    let change_aztec_format = {
+        let dispatch = props.dispatch.clone();
+        move |format| {
+            let dispatch = dispatch.clone();
+            move |_| {
+                dispatch.emit(AppAction::UpdateAztecFormat(format));
+            }
+        }
+    };
+
+    let change_ec_percent = {
+        let dispatch = props.dispatch.clone();
+        move |e: Event| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            if let Ok(percent) = input.value().parse::<u8>() {
+                dispatch.emit(AppAction::UpdateAztecEcPercent(percent.clamp(MIN_EC_PERCENT, MAX_EC_PERCENT)));
+            }
+        }
+    };
+
+    // an empty option value allows any size
+    let change_rmqr_size = {
+        let dispatch = props.dispatch.clone();
+        let QrOptions { rmqr_height, rmqr_width, .. } = props.options;
+        move |is_height: bool| {
+            let dispatch = dispatch.clone();
+            move |e: Event| {
+                let select = e.target_unchecked_into::<HtmlSelectElement>();
+                let modules = select.value().parse::<usize>().ok();
+                let (height, width) = if is_height { (modules, rmqr_width) } else { (rmqr_height, modules) };
+                dispatch.emit(AppAction::UpdateRmqrSize(height, width));
+            }
+        }
+    };
+
+    // an empty option value picks the column count
+    let change_pdf417_columns = {
+        let dispatch = props.dispatch.clone();
+        move |e: Event| {
+            let select = e.target_unchecked_into::<HtmlSelectElement>();
+            dispatch.emit(AppAction::UpdatePdf417Columns(select.value().parse::<usize>().ok()));
+        }
+    };
+
+    // an empty option value picks the level recommended for the data
+    let change_security_level = {
+        let dispatch = props.dispatch.clone();
+        move |e: Event| {
+            let select = e.target_unchecked_into::<HtmlSelectElement>();
+            dispatch.emit(AppAction::UpdatePdf417SecurityLevel(select.value().parse::<u8>().ok()));
+        }
+    };
+
+    // option values are indices into Charset::ALL, with an empty value for no ECI
+    let change_eci = {
+        let dispatch = props.dispatch.clone();
+        move |e: Event| {
+            let select = e.target_unchecked_into::<HtmlSelectElement>();
+            let eci = select.value().parse::<usize>().ok().and_then(|i| Charset::ALL.get(i).copied());
+            dispatch.emit(AppAction::UpdateEci(eci));
+        }
+    };
+
+    let change_kanji_mode = {
+        let dispatch = props.dispatch.clone();
+        move |e: Event| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            dispatch.emit(AppAction::UpdateKanjiMode(input.checked()));
+        }
+    };
+
+    let change_human_readable = {
+        let dispatch = props.dispatch.clone();
+        move |e: Event| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            dispatch.emit(AppAction::UpdateHumanReadable(input.checked()));
+        }
+    };
+
+    // an empty value goes back to the symbology's own quiet zone
+    let change_quiet_zone = {
+        let dispatch = props.dispatch.clone();
+        move |e: Event| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            let modules = input.value().parse::<usize>().ok().map(|modules| modules.min(MAX_QUIET_ZONE));
+            dispatch.emit(AppAction::UpdateQuietZone(modules));
+        }
+    };
+
+    let change_structured_append = {
+        let dispatch = props.dispatch.clone();
+        move |e: Event| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            dispatch.emit(AppAction::UpdateStructuredAppend(input.checked()));
+        }
+    };
+
+    // per-symbol details only make sense when there is exactly one symbol
+    let code = match props.codes.as_slice() {
+        [code] => Some(code),
+        _ => None,
+    };
+    let chosen_ec_level = props.codes.first()
+        .filter(|_| props.options.auto_ec_level)
+        .and_then(|code| code.ec_level());
+    let symbology = props.options.symbology;
+    let mask_count = if symbology.is_qr() { mask_count(symbology.version(symbology.min_version())) } else { 0 };
+    let mask_label = |mask: u8| {
+        let penalty = code.and_then(|code| code.mask_penalties().get(mask as usize).copied());
+        let class = classes!(code.filter(|code| code.mask() == Some(mask)).map(|_| "chosen-mask"));
+        html! {
+            <label for={ format!("mask-{mask}") } {class}>
+                { mask }
+                if let Some(penalty) = penalty {
+                    <span class="penalty">{ format!(" ({penalty})") }</span>
+                }
+            </label>
+        }
+    };
+
+    let visible_onclick = {
+        let options_visible = options_visible.clone();
+        move |_| {
+            options_visible.set(!*options_visible);
+        }
+    };
+    
+    let button_message = if *options_visible {
+        "Hide Advanced Options"
+    } else {
+        "Show Advanced Options"
+    };
+    
+    html! {
+        <>
+            if *options_visible {
+                <div class="advanced-options">
+                    <div class="change-symbology">
+                        { "Symbology: " }
+                        { for Symbology::ALL.into_iter().map(|option| html! {
+                            <>
+                                <input type="radio" name="symbology" id={ format!("{option:?}") } onclick={ change_symbology(option) } checked={ symbology == option }/>
+                                <label for={ format!("{option:?}") }>{ option.to_string() }</label>
+                            </>
+                        }) }
+                    </div>
+                    if symbology == Symbology::DataMatrix {
+                        <div class="change-shape">
+                            { "Shape: " }
+                            { for DataMatrixShape::ALL.into_iter().map(|shape| html! {
+                                <>
+                                    <input type="radio" name="shape" id={ format!("shape-{shape:?}") } onclick={ change_shape(shape) } checked={ props.options.data_matrix_shape == shape }/>
+                                    <label for={ format!("shape-{shape:?}") }>{ shape.to_string() }</label>
+                                </>
+                            }) }
+                            if let Some(code) = code {
+                                <p class="hint">{ format!("Using the smallest size that fits, {}.", code.size()) }</p>
+                            }
+                        </div>
+                    }
+                    if symbology == Symbology::Aztec {
+                        <div class="change-aztec">
+                            { "Format: " }
+                            { for AztecFormat::ALL.into_iter().map(|format| html! {
+                                <>
+                                    <input type="radio" name="aztec-format" id={ format!("aztec-{format:?}") } onclick={ change_aztec_format(format) } checked={ props.options.aztec_format == format }/>
+                                    <label for={ format!("aztec-{format:?}") }>{ format.to_string() }</label>
+                                </>
+                            }) }
+                            <div class="change-ec-percent">
+                                { "Error correction: " }
+                                <input type="number" min={ MIN_EC_PERCENT.to_string() } max={ MAX_EC_PERCENT.to_string() } value={ props.options.aztec_ec_percent.to_string() } onchange={change_ec_percent}/>
+                                { "%" }
+                            </div>
+                            if let Some(code) = code {
+                                <p class="hint">{ format!("Using the smallest symbol that fits, {}.", code.size()) }</p>
+                            }
+                        </div>
+                    }
+                    if symbology == Symbology::Rmqr {
+                        <div class="change-rmqr">
+                            { "Height: " }
+                            <select onchange={ change_rmqr_size(true) }>
+                                <option value="" selected={ props.options.rmqr_height.is_none() }>{ "Any" }</option>
+                                { for RMQR_HEIGHTS.into_iter().map(|height| html! {
+                                    <option value={ height.to_string() } selected={ props.options.rmqr_height == Some(height) }>{ height }</option>
+                                }) }
+                            </select>
+                            { " Width: " }
+                            <select onchange={ change_rmqr_size(false) }>
+                                <option value="" selected={ props.options.rmqr_width.is_none() }>{ "Any" }</option>
+                                { for RMQR_WIDTHS.into_iter().map(|width| html! {
+                                    <option value={ width.to_string() } selected={ props.options.rmqr_width == Some(width) }>{ width }</option>
+                                }) }
+                            </select>
+                            if let Some(code) = code {
+                                <p class="hint">{ format!("Using the smallest size that fits, {}.", code.size()) }</p>
+                            }
+                        </div>
+                    }
+                    if symbology == Symbology::Pdf417 {
+                        <div class="change-pdf417">
+                            { "Columns: " }
+                            <select onchange={change_pdf417_columns}>
+                                <option value="" selected={ props.options.pdf417_columns.is_none() }>{ "Auto" }</option>
+                                { for (1..=MAX_PDF417_COLUMNS).map(|columns| html! {
+                                    <option value={ columns.to_string() } selected={ props.options.pdf417_columns == Some(columns) }>{ columns }</option>
+                                }) }
+                            </select>
+                            { " Security level: " }
+                            <select onchange={change_security_level}>
+                                <option value="" selected={ props.options.pdf417_security_level.is_none() }>{ "Auto" }</option>
+                                { for (0..=MAX_SECURITY_LEVEL).map(|level| html! {
+                                    <option value={ level.to_string() } selected={ props.options.pdf417_security_level == Some(level) }>
+                                        { format!("{level} ({} check codewords)", 2 << level) }
+                                    </option>
+                                }) }
+                            </select>
+                            if let Some(code) = code {
+                                <p class="hint">{ format!("{}.", code.size()) }</p>
+                            }
+                        </div>
+                    }
+                    if symbology.is_linear() {
+                        <div class="change-human-readable">
+                            <input type="checkbox" id="human-readable" onchange={change_human_readable} checked={ props.options.human_readable }/>
+                            <label for="human-readable">{ "Print the data under the bars" }</label>
+                            if let Some(code) = code {
+                                <p class="hint">{ format!("{}.", code.size()) }</p>
+                            }
+                        </div>
+                    }
+                    if symbology.is_qr_family() {
+                        <div class="change-ec">
+                            { "Error correction level: " }
+                            <input type="radio" name="eclevel" id="ec-auto" onclick={ change_ec(None) } checked={ props.options.auto_ec_level }/>
+                            <label for="ec-auto">{ "Auto" }</label>
+                            { for [(EcLevel::L, "7%"), (EcLevel::M, "15%"), (EcLevel::Q, "25%"), (EcLevel::H, "30%")].into_iter().filter(|&(level, _)| symbology != Symbology::Rmqr || matches!(level, EcLevel::M | EcLevel::H)).map(|(level, text)| html! {
+                                <>
+                                    <input type="radio" name="eclevel" id={ format!("{level:?}") } onclick={ change_ec(Some(level)) } checked={ !props.options.auto_ec_level && props.options.ec_level == level }/>
+                                    <label for={ format!("{level:?}") } class={ classes!(chosen_ec_level.filter(|&chosen| chosen == level).map(|_| "chosen-ec")) }>{ text }</label>
+                                </>
+                            }) }
+                            if props.options.auto_ec_level && chosen_ec_level.is_some() {
+                                <p class="hint">{ "Using the highest level that fits the allowed versions, highlighted." }</p>
+                            }
+                        </div>
+                    }
+                    if symbology.is_qr() {
+                        <div class="change-version">
+                            if symbology == Symbology::MicroQr { { "Version: M" } } else { { "Version: " } }
+                            <input type="number" min="1" max={ symbology.max_version().to_string() } value={ props.options.min_version.to_string() } onchange={ change_version(true) }/>
+                            if symbology == Symbology::MicroQr { { " to M" } } else { { " to " } }
+                            <input type="number" min="1" max={ symbology.max_version().to_string() } value={ props.options.max_version.to_string() } onchange={ change_version(false) }/>
+                        </div>
+                        <div class="change-mask">
+                            { "Mask: " }
+                            <input type="radio" name="mask" id="mask-auto" onclick={ change_mask(None) } checked={ props.options.mask.is_none() }/>
+                            <label for="mask-auto">{ "Auto" }</label>
+                            { for (0..mask_count).map(|mask| html! {
+                                <>
+                                    <input type="radio" name="mask" id={ format!("mask-{mask}") } onclick={ change_mask(Some(mask)) } checked={ props.options.mask == Some(mask) }/>
+                                    { mask_label(mask) }
+                                </>
+                            }) }
+                            if code.is_some() {
+                                <p class="hint">{ "Penalty scores in parentheses, lower is better. The mask in use is highlighted." }</p>
+                            }
+                        </div>
+                    }
+                    <div class="change-eci">
+                        { "Character set (ECI): " }
+                        <select onchange={change_eci} disabled={ symbology == Symbology::MicroQr || symbology.is_linear() }>
+                            <option value="" selected={ props.options.eci.is_none() }>{ "None (raw bytes)" }</option>
+                            { for Charset::ALL.iter().enumerate().map(|(i, &charset)| html! {
+                                <option value={ i.to_string() } selected={ props.options.eci == Some(charset) }>{ charset.to_string() }</option>
+                            }) }
+                        </select>
+                        if symbology == Symbology::MicroQr {
+                            <p class="hint">{ "Micro QR codes cannot declare a character set." }</p>
+                        } else if symbology.is_linear() {
+                            <p class="hint">{ "Linear barcodes cannot declare a character set." }</p>
+                        }
+                    </div>
+                    if symbology.is_qr_family() {
+                        <div class="change-kanji-mode">
+                            <input type="checkbox" id="kanji-mode" onchange={change_kanji_mode} checked={ props.options.kanji_mode } disabled={ props.options.eci.is_some() }/>
+                            <label for="kanji-mode">{ "Kanji mode (convert text to Shift-JIS)" }</label>
+                            if props.options.eci.is_some() {
+                                <p class="hint">{ "Pick Shift-JIS as the character set to use Kanji mode with ECI." }</p>
+                            }
+                            if let Some(savings) = props.request.as_ref().zip(code).and_then(|(request, code)| request.kanji_savings(code)) {
+                                <p class="hint">{ kanji_savings_text(savings) }</p>
+                            }
+                        </div>
+                    }
+                    if symbology.is_qr() {
+                        <div class="change-structured-append">
+                            <input type="checkbox" id="structured-append" onchange={change_structured_append} checked={ props.options.structured_append } disabled={ symbology == Symbology::MicroQr }/>
+                            <label for="structured-append">{ format!("Split data that is too large across up to {MAX_SYMBOLS} linked QR codes (Structured Append)") }</label>
+                            if props.codes.len() > 1 {
+                                <p class="hint">{ format!("Split across {} codes. Scan all of them with a reader that supports Structured Append.", props.codes.len()) }</p>
+                            }
+                        </div>
+                        if let (Some(request), Some(code), Some(version)) = (&props.request, code, code.and_then(GeneratedQr::version)) {
+                            <SegmentEditor
+                                data={code.payload().to_vec()}
+                                segments={code.segments().to_vec()}
+                                {version}
+                                manual={request.segments.is_some()}
+                                onchange={
+                                    let dispatch = props.dispatch.clone();
+                                    move |segments| dispatch.emit(AppAction::UpdateSegments(segments))
+                                }
+                            />
+                        }
+                    }
+                    <div class="change-quiet-zone">
+                        { "Quiet zone: " }
+                        <input type="number" min="0" max={ MAX_QUIET_ZONE.to_string() } placeholder={ symbology.quiet_zone().to_string() } value={ props.options.quiet_zone.map(|modules| modules.to_string()).unwrap_or_default() } onchange={change_quiet_zone}/>
+                        { " modules" }
+                        if props.options.quiet_zone.is_some_and(|modules| modules < symbology.quiet_zone()) {
+                            <p class="hint quiet-zone-warning">{ format!("{symbology} asks for at least {} modules of margin; scanners may not find a code with less.", symbology.quiet_zone()) }</p>
+                        } else {
+                            <p class="hint">{ format!("Leave empty for the {} modules {symbology} asks for.", symbology.quiet_zone()) }</p>
+                        }
+                    </div>
+                    <div class="change-color">
+                        <ColorInput 
+                            text="Background Color"
+                            color={props.options.background_color}
+                            onchange={
+                                let dispatch = props.dispatch.clone();
+                                move |color| dispatch.emit(AppAction::UpdateBackgroundColor(color))
+                            }
+                        />
+                        <ColorInput 
+                            text="Foreground Color"
+                            color={props.options.foreground_color}
+                            onchange={
+                                let dispatch = props.dispatch.clone();
+                                move |color| dispatch.emit(AppAction::UpdateForegroundColor(color))
+                            }
+                        />
+                    </div>
+                </div>
+            }
+            <input type="button" value={button_message} onclick={visible_onclick}/>
+        </>
+    }
+}
+
+enum AppAction {
+    /// `None` picks the highest level that fits.
+    UpdateEcLevel(Option<EcLevel>),
+    CloseError,
+    GenerateQrCode(Vec<u8>),
+    /// Encode the contents of a file, splitting it with Structured Append
+    /// unless the version range was narrowed.
+    GenerateFile(Vec<u8>),
+    GenerateGs1(Gs1Data, Gs1Format),
+    UpdateBackgroundColor(Color),
+    UpdateForegroundColor(Color),
+    UpdateVersionRange(i16, i16),
+    UpdateMask(Option<u8>),
+    UpdateSymbology(Symbology),
+    UpdateSegments(Option<Vec<Segment>>),
+    UpdateEci(Option<Charset>),
+    UpdateKanjiMode(bool),
+    UpdateStructuredAppend(bool),
+    UpdateDataMatrixShape(DataMatrixShape),
+    UpdateAztecFormat(AztecFormat),
+    UpdateAztecEcPercent(u8),
+    UpdateRmqrSize(Option<usize>, Option<usize>),
+    /// `None` picks the column count, or the security level, for the data.
+    UpdatePdf417Columns(Option<usize>),
+    UpdatePdf417SecurityLevel(Option<u8>),
+    UpdateHumanReadable(bool),
+    /// `None` uses the symbology's own quiet zone.
+    UpdateQuietZone(Option<usize>),
+    /// Send a file as a fountain-coded stream with this block size.
+    GenerateStream(Vec<u8>, usize),
+    /// Allow saving codes that failed to scan back.
+    UpdateExportAnyway(bool),
+    /// Encode a code read from an image again with the current options.
+    Regenerate(DecodedQr),
+    /// The results of reading back every current code, once they are saved.
+    UpdateChecks(Vec<Result<(), VerifyError>>),
+    ShowError(String),
+}
+
+#[derive(Clone, Debug)]
+struct QrInfo {
+    request: QrRequest,
+    /// A single symbol, or every symbol of a Structured Append sequence.
+    codes: Vec<GeneratedQr>,
+    svgs: Vec<String>,
+    /// The result of reading the first `checks.len()` of `codes` back from
+    /// their rendered images. Only the first is read when generating, the
+    /// rest when they are saved, as a stream may have hundreds of frames.
+    checks: Vec<Result<(), VerifyError>>,
+    /// Block size of a fountain-coded stream, whose frames are `codes`.
+    stream: Option<usize>,
+}
+
+impl QrInfo {
+    fn fails_to_scan(&self) -> bool {
+        self.checks.iter().any(|check| check.as_ref().is_err_and(VerifyError::is_failure))
+    }
+
+    // the checks with every code not read back yet added
+    fn check_all(&self) -> Vec<Result<(), VerifyError>> {
+        let mut checks = self.checks.clone();
+        checks.extend(self.codes[checks.len()..].iter().map(|code| code.verify(DEFAULT_MODULE_SIZE)));
+        checks
+    }
+}
+
+#[derive(Clone, Debug)]
+struct AppState {
+    qr: Option<QrInfo>,
+    error: Option<String>,
+    options: QrOptions,
+    /// Save the current codes even though they do not scan back.
+    export_anyway: bool,
+}
+
+impl AppState {
+    fn generate(&self, request: QrRequest, stream: Option<usize>) -> Rc<Self> {
+        let codes = match stream {
+            Some(block_size) => request.generate_stream(block_size, DEFAULT_REDUNDANCY_PERCENT),
+            None => request.generate_set(),
+        };
+        match codes {
+            Ok(codes) => {
+                let svgs = codes.iter().map(GeneratedQr::to_svg).collect();
+                let checks = codes.iter().take(1).map(|code| code.verify(DEFAULT_MODULE_SIZE)).collect();
+                AppState { qr: Some(QrInfo { request, codes, svgs, checks, stream }), export_anyway: false, ..self.clone() }
+            },
+            Err(e) => {
+                AppState { error: Some(e.to_string()), ..self.clone() }
+            }
+        }.into()
+    }
+
+    // regenerates the current code, if any, so it reflects the new options
+    fn update_options(&self, options: QrOptions) -> Rc<Self> {
+        let new_state = AppState { options, ..self.clone() };
+        if let Some(qr_info) = &self.qr {
+            new_state.generate(QrRequest { options, ..qr_info.request.clone() }, qr_info.stream)
+        } else {
+            new_state.into()
+        }
+    }
+
+    // like `update_options`, but manual segments index into the transcoded
+    // payload, which changes with the encoding, so they are dropped
+    fn update_encoding(&self, options: QrOptions) -> Rc<Self> {
+        let new_state = AppState { options, ..self.clone() };
+        if let Some(qr_info) = &self.qr {
+            new_state.generate(QrRequest { options, segments: None, ..qr_info.request.clone() }, qr_info.stream)
+        } else {
+            new_state.into()
+        }
+    }
+}
+
+impl Reducible for AppState {
+    type Action = AppAction;
+    
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        match action {
+            AppAction::CloseError => {
+                AppState { error: None, ..(*self).clone() }.into()
+            },
+            AppAction::ShowError(message) => {
+                AppState { error: Some(message), ..(*self).clone() }.into()
+            },
+            AppAction::GenerateQrCode(data) => {
+                self.generate(QrRequest::new(data, self.options), None)
+            },
+            AppAction::GenerateFile(data) => {
+                let QrOptions { symbology, min_version, max_version, .. } = self.options;
+                let full_range = min_version == symbology.min_version() && max_version == symbology.max_version();
+                // splitting a large file applies to this request only, not to the saved options
+                let options = QrOptions { structured_append: self.options.structured_append || full_range, ..self.options };
+                self.generate(QrRequest::new(data, options), None)
+            },
+            AppAction::GenerateStream(data, block_size) => {
+                self.generate(QrRequest::new(data, self.options), Some(block_size))
+            },
+            AppAction::Regenerate(decoded) => {
+                self.generate(decoded.to_request(self.options), None)
+            },
+            AppAction::GenerateGs1(data, format) => {
+                match QrRequest::from_gs1(&data, &format, self.options) {
+                    Ok(request) => self.generate(request, None),
+                    Err(e) => AppState { error: Some(e.to_string()), ..(*self).clone() }.into()
+                }
+            },
+            AppAction::UpdateEcLevel(ec_level) => {
+                self.update_options(QrOptions {
+                    ec_level: ec_level.unwrap_or(self.options.ec_level),
+                    auto_ec_level: ec_level.is_none(),
+                    ..self.options
+                })
+            },
+            AppAction::UpdateBackgroundColor(background_color) => {
+                self.update_options(QrOptions { background_color, ..self.options })
+            },
+            AppAction::UpdateForegroundColor(foreground_color) => {
+                self.update_options(QrOptions { foreground_color, ..self.options })
+            },
+            AppAction::UpdateVersionRange(min_version, max_version) => {
+                self.update_options(QrOptions { min_version, max_version, ..self.options })
+            },
+            AppAction::UpdateMask(mask) => {
+                self.update_options(QrOptions { mask, ..self.options })
+            },
+            // version numbers and masks mean different things in each
+            // symbology, and rMQR only has levels M and H
+            AppAction::UpdateSymbology(symbology) => {
+                let ec_level = match (symbology, self.options.ec_level) {
+                    (Symbology::Rmqr, EcLevel::L) => EcLevel::M,
+                    (Symbology::Rmqr, EcLevel::Q) => EcLevel::H,
+                    (_, ec_level) => ec_level,
+                };
+                self.update_options(QrOptions {
+                    symbology,
+                    ec_level,
+                    min_version: symbology.min_version(),
+                    max_version: symbology.max_version(),
+                    mask: None,
+                    ..self.options
+                })
+            },
+            AppAction::UpdateStructuredAppend(structured_append) => {
+                self.update_options(QrOptions { structured_append, ..self.options })
+            },
+            AppAction::UpdateDataMatrixShape(data_matrix_shape) => {
+                self.update_options(QrOptions { data_matrix_shape, ..self.options })
+            },
+            AppAction::UpdateAztecFormat(aztec_format) => {
+                self.update_options(QrOptions { aztec_format, ..self.options })
+            },
+            AppAction::UpdateAztecEcPercent(aztec_ec_percent) => {
+                self.update_options(QrOptions { aztec_ec_percent, ..self.options })
+            },
+            AppAction::UpdateRmqrSize(rmqr_height, rmqr_width) => {
+                self.update_options(QrOptions { rmqr_height, rmqr_width, ..self.options })
+            },
+            AppAction::UpdatePdf417Columns(pdf417_columns) => {
+                self.update_options(QrOptions { pdf417_columns, ..self.options })
+            },
+            AppAction::UpdatePdf417SecurityLevel(pdf417_security_level) => {
+                self.update_options(QrOptions { pdf417_security_level, ..self.options })
+            },
+            AppAction::UpdateHumanReadable(human_readable) => {
+                self.update_options(QrOptions { human_readable, ..self.options })
+            },
+            AppAction::UpdateQuietZone(quiet_zone) => {
+                self.update_options(QrOptions { quiet_zone, ..self.options })
+            },
+            AppAction::UpdateEci(eci) => {
+                self.update_encoding(QrOptions { eci, ..self.options })
+            },
+            AppAction::UpdateKanjiMode(kanji_mode) => {
+                self.update_encoding(QrOptions { kanji_mode, ..self.options })
+            },
+            AppAction::UpdateChecks(checks) => {
+                match &self.qr {
+                    Some(qr) if qr.codes.len() == checks.len() => {
+                        AppState { qr: Some(QrInfo { checks, ..qr.clone() }), ..(*self).clone() }.into()
+                    },
+                    _ => self,
+                }
+            },
+            AppAction::UpdateExportAnyway(export_anyway) => {
+                AppState { export_anyway, ..(*self).clone() }.into()
+            },
+            AppAction::UpdateSegments(segments) => {
+                match &self.qr {
+                    Some(qr_info) => self.generate(qr_info.request.clone().with_segments(segments), qr_info.stream),
+                    None => self
+                }
+            },
+        }
+    }
+}
+
+// reads back whichever codes have not been yet, and whether they may be saved
+fn ready_to_save(state: &UseReducerHandle<AppState>, qr: &QrInfo) -> bool {
+    let qr = if qr.checks.len() < qr.codes.len() {
+        let checks = qr.check_all();
+        state.dispatch(AppAction::UpdateChecks(checks.clone()));
+        QrInfo { checks, ..qr.clone() }
+    } else {
+        qr.clone()
+    };
+    !qr.fails_to_scan() || state.export_anyway
+}
+
+#[function_component(App)]
+pub fn app() -> Html {
+    let state = use_reducer(|| AppState { 
+        qr: None, 
+        error: None, 
+        options: QrOptions::default(),
+        export_anyway: false,
+    });
+    let svgs = state.qr.as_ref().map(|qr| qr.svgs.clone()).unwrap_or_default();
+    let checks = state.qr.as_ref().map(|qr| qr.checks.clone()).unwrap_or_default();
+    let fails_to_scan = state.qr.as_ref().is_some_and(QrInfo::fails_to_scan);
+    let export_blocked = fails_to_scan && !state.export_anyway;
+    let link_ref = use_node_ref();
+
+    let dispatch = {
+        let state = state.clone();
+        move |action| {
+            state.dispatch(action)
+        }
+    };
+
+    let error_close = {
+        let state = state.clone();
+        move |_| {
+            state.dispatch(AppAction::CloseError)
+        }
+    };
+
+    let generate = {
+        let state = state.clone();
+        move |data: Vec<u8>| {
+            state.dispatch(AppAction::GenerateQrCode(data))
+        }
+    };
+
+    let generate_file = {
+        let state = state.clone();
+        move |data: Vec<u8>| {
+            state.dispatch(AppAction::GenerateFile(data))
+        }
+    };
+
+    let stream = {
+        let state = state.clone();
+        move |(data, block_size): (Vec<u8>, usize)| {
+            state.dispatch(AppAction::GenerateStream(data, block_size))
+        }
+    };
+
+    let save_gif = {
+        let state = state.clone();
+        let link_ref = link_ref.clone();
+
+        move |_: MouseEvent| {
+            if let Some(qr) = state.qr.as_ref().filter(|qr| ready_to_save(&state, qr)) {
+                let gif = match encode_gif(&qr.codes, DEFAULT_MODULE_SIZE, DEFAULT_FRAME_DELAY_MS) {
+                    Ok(gif) => gif,
+                    Err(e) => return state.dispatch(AppAction::ShowError(e.to_string())),
+                };
+                let link = link_ref.cast::<HtmlAnchorElement>().expect("input_ref not bound to anchor!");
+                let data = general_purpose::STANDARD_NO_PAD.encode(gif);
+                link.set_href(&format!("data:image/gif;base64,{data}"));
+                link.set_download("qr-stream.gif");
+                link.click();
+            }
+        }
+    };
+
+    let save = {
+        let state = state.clone();
+        let link_ref = link_ref.clone();
+
+        move |save_type: ImageFormat| {
+            move |_: MouseEvent| {
+                if let Some(qr) = state.qr.as_ref().filter(|qr| ready_to_save(&state, qr)) {
+                    let link = link_ref.cast::<HtmlAnchorElement>().expect("input_ref not bound to anchor!");
+                    let total = qr.codes.len();
+                    for (index, code) in qr.codes.iter().enumerate() {
+                        let data = general_purpose::STANDARD_NO_PAD.encode(code.encode(save_type, DEFAULT_MODULE_SIZE));
+                        let name = match total {
+                            1 => String::from("qr"),
+                            _ => format!("qr-{}-of-{total}", index + 1),
+                        };
+
+                        link.set_href(&format!("data:{};base64,{}", save_type.mime(), data));
+                        link.set_download(&format!("{name}.{}", save_type.extension()));
+                        link.click();
+                    }
+                }
+            }
+        }
+    };
+
+    html! {
+        <>
+        <div class="main-container">
+        <main>
+            <a style="diplay: none;" ref={link_ref}></a>
+            <h1>{ "QR Code Generator" }</h1>
+            <QrOutput qrs={svgs} {checks} animate={ state.qr.as_ref().is_some_and(|qr| qr.stream.is_some()) }/>
+            if fails_to_scan {
+                <div class="scan-warning">
+                    <p>{ "The rendered code does not scan back as its data. Try colors with more contrast or a higher error correction level." }</p>
+                    <input type="checkbox" id="export-anyway" checked={state.export_anyway} onchange={
+                        let state = state.clone();
+                        move |e: Event| state.dispatch(AppAction::UpdateExportAnyway(e.target_unchecked_into::<HtmlInputElement>().checked()))
+                    }/>
+                    <label for="export-anyway">{ "Save it anyway" }</label>
+                </div>
+            }
+            if state.qr.as_ref().is_some_and(|qr| qr.stream.is_some()) {
+                <div class="save-buttons">
+                    <input type="button" value="Save as GIF" onclick={save_gif} disabled={export_blocked}/>
+                </div>
+            } else if state.qr.is_some() {
+                <div class="save-buttons">
+                    <input type="button" value="Save as PNG" onclick={ let save = save.clone(); save(ImageFormat::Png) } disabled={export_blocked}/>
+                    <input type="button" value="Save as JPEG" onclick={ let save = save.clone(); save(ImageFormat::Jpeg) } disabled={export_blocked}/>
+                    <input type="button" value="Save as SVG" onclick={ let save = save.clone(); save(ImageFormat::Svg)} disabled={export_blocked}/>
+                </div>
+            }
+            if state.qr.as_ref().is_some_and(|qr| qr.stream.is_none()) {
+                <StressReport codes={state.qr.as_ref().map(|qr| qr.codes.clone()).unwrap_or_default()}/>
+            }
+            if let Some(message) = &state.error {
+                <ErrorPopup message={message.clone()} close={error_close} />
+            }
+            <Options 
+                options={state.options}
+                request={state.qr.as_ref().map(|qr| qr.request.clone())}
+                // a stream's frames have no per-symbol details worth showing
+                codes={state.qr.as_ref().filter(|qr| qr.stream.is_none()).map(|qr| qr.codes.clone()).unwrap_or_default()}
+                {dispatch}
+            />
+            <TextInput {generate} options={state.options}/>
+            <FileInput generate={generate_file} {stream}/>
+            <ImageInput regenerate={
+                let state = state.clone();
+                move |decoded| state.dispatch(AppAction::Regenerate(decoded))
+            }/>
+            <Gs1Editor generate={
+                let state = state.clone();
+                move |(data, format)| state.dispatch(AppAction::GenerateGs1(data, format))
+            }/>
+        </main>
+        </div>
+        <footer><a href="https://github.com/RainbowAsteroids/qr-portal2d" target="_blank" rel="noopener noreferrer">{ "Source code" }</a></footer>
+        </>
+    }
+}
@@ -0,0 +1,638 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::{env, fs, io};
+
+use qr_portal2d::color::Color;
+use qr_portal2d::core::compression::{self, Compression};
+use qr_portal2d::core::decode;
+use qr_portal2d::core::fountain::{self, FountainDecoder};
+use qr_portal2d::core::gs1::{Gs1Data, Gs1Format, DEFAULT_RESOLVER};
+use qr_portal2d::core::segment::{mode_name, Mode, Segment};
+use qr_portal2d::core::stress;
+use qr_portal2d::core::{encode_gif, AztecFormat, Charset, DataMatrixShape, EcLevel, GeneratedQr, ImageFormat, QrOptions, QrRequest, SymbolSize, Symbology, DEFAULT_FRAME_DELAY_MS, DEFAULT_MODULE_SIZE, MAX_QUIET_ZONE};
+
+const USAGE: &str = "\
+Usage: qr-portal2d-cli [OPTIONS] --output <PATH> [TEXT]
+       qr-portal2d-cli --unpack <PATH> [--output <PATH>]
+       qr-portal2d-cli --receive [--output <PATH>] <FRAME>...
+       qr-portal2d-cli --read <IMAGE>...
+
+Encodes TEXT, the contents of --file, GS1 data, or standard input as a QR code,
+rMQR code, Data Matrix, Aztec code, PDF417 or linear barcode. With --unpack,
+restores the file in a compressed payload read from a scanned code. With
+--receive, reassembles a file from scanned frames of a --stream, one payload per
+FRAME file. With --read, prints the data of every QR code in PNG or JPEG
+images.
+
+Options:
+  -o, --output <PATH>       File to write; the format follows the extension
+  -f, --file <PATH>         Encode the bytes of a file instead of TEXT
+      --gs1 <DATA>          Encode GS1 data written as (AI)value pairs, e.g.
+                            (01)09506000134352(17)251231(10)ABC123(21)42, as GS1 QR
+      --compress <METHOD>   Compress the data with deflate or zstd behind a header
+                            that --unpack reads back [default: none]
+      --unpack <PATH>       Restore the file in a compressed payload saved at PATH, or
+                            - for standard input, to --output or the stored name
+      --stream              Send the data as a looping fountain-coded animation, written
+                            as an animated GIF for a .gif output and as numbered
+                            frames otherwise; any large enough set of scanned frames
+                            rebuilds the file
+      --block-size <BYTES>  Bytes of data in each --stream frame [default: 200]
+      --redundancy <N>      Extra --stream frames, as a percentage of the frames the
+                            data needs [default: 50]
+      --frame-delay <MS>    Time each GIF frame is shown [default: 250]
+      --receive             Reassemble the file from the FRAME files instead of encoding
+      --read                Print the data of every QR code in the IMAGE files, as
+                            text or, for binary data, as hex
+      --digital-link        Encode the --gs1 data as a GS1 Digital Link URI instead,
+                            the only way PDF417 holds it
+      --resolver <URL>      Domain of the Digital Link [default: https://id.gs1.org]
+      --format <FORMAT>     png, jpeg, svg, or gif for a --stream (overrides the output
+                            extension)
+  -s, --symbology <KIND>    qr, micro, rmqr, datamatrix, aztec, pdf417, code128, ean13
+                            or upca [default: qr]
+      --rmqr-size <HxW>     rMQR height and width in modules, e.g. 7x139, either of
+                            which may be * for any [default: *x*]
+      --shape <SHAPE>       Data Matrix sizes to pick from: square or rectangle
+                            [default: square]
+      --aztec-format <FMT>  auto, compact or full [default: auto]
+      --ec-percent <N>      Share of an Aztec code used for error correction,
+                            5-95 [default: 23]
+      --columns <N>         PDF417 data columns, 1-30, or auto for a symbol about
+                            twice as wide as high [default: auto]
+      --security-level <N>  PDF417 error correction, 0-8, or auto for the level the
+                            standard recommends for the data [default: auto]
+  -e, --ec-level <LEVEL>    Error correction level: L, M, Q, H (rMQR only has M and H),
+                            or auto for the highest level that fits the allowed
+                            versions [default: M]
+      --qr-version <RANGE>  Version to use, either N or MIN-MAX [default: every version]
+      --mask <MASK>         Mask pattern 0-7, or auto for the lowest penalty [default: auto]
+      --eci <CHARSET>       Transcode the text into CHARSET and declare it with an ECI
+                            header: utf-8, iso-8859-1, iso-8859-2, iso-8859-5,
+                            iso-8859-7, iso-8859-15, shift-jis, windows-1251,
+                            windows-1252, big5, gb18030 or euc-kr [default: none]
+      --kanji               Convert the text to Shift-JIS so kanji use the compact
+                            Kanji mode (text Shift-JIS lacks stays UTF-8)
+      --no-text             Leave the human-readable text off linear barcodes
+      --foreground <COLOR>  Module color as #rrggbb [default: #000000]
+      --background <COLOR>  Background color as #rrggbb [default: #ffffff]
+      --scale <PIXELS>      Pixels per module of PNG/JPEG output [default: 8]
+      --quiet-zone <N>      Modules of blank margin around the symbol, 0-20 [default: 4
+                            for QR, 2 for Micro QR, rMQR and PDF417, 1 for Data Matrix, 0 for
+                            Aztec, 9-11 for linear barcodes]
+      --segments <SPEC>     Encode with these segments instead of the optimal ones, as
+                            comma separated MODE:LENGTH pairs where MODE is n(umeric),
+                            a(lphanumeric), b(yte) or k(anji) and the last LENGTH may
+                            be * for the rest of the data, e.g. n:12,b:*
+      --split               Split data that is too large into up to 16 linked QR codes
+                            (Structured Append) [default: on for --file without
+                            --qr-version]
+      --single              Fail instead of splitting data that is too large
+      --force               Write QR codes even if reading the rendered image back
+                            does not give the data
+      --stress-test         Report how much blur, noise, skew, fading and covering
+                            each QR code still scans through
+  -v, --verbose             Print the chosen size, mask and segments
+  -h, --help                Print this message
+";
+
+enum Payload {
+    Text(String),
+    Gs1(Gs1Data, Gs1Format),
+    File(PathBuf),
+    Stdin,
+}
+
+enum Command {
+    Generate(Box<Args>),
+    Unpack { source: PathBuf, output: Option<PathBuf> },
+    Receive { frames: Vec<PathBuf>, output: Option<PathBuf> },
+    Read { images: Vec<PathBuf> },
+}
+
+// the frames of a fountain-coded stream
+struct Stream {
+    block_size: usize,
+    redundancy_percent: usize,
+    frame_delay: u32,
+}
+
+struct Args {
+    payload: Payload,
+    compress: Option<Compression>,
+    output: PathBuf,
+    /// `None` for an animated GIF of a stream.
+    format: Option<ImageFormat>,
+    stream: Option<Stream>,
+    options: QrOptions,
+    scale: u32,
+    segments: Option<Vec<(Mode, Option<usize>)>>,
+    force: bool,
+    stress_test: bool,
+    verbose: bool,
+}
+
+// `None` for auto
+fn parse_ec_level(level: &str) -> Result<Option<EcLevel>, String> {
+    match level.to_ascii_uppercase().as_str() {
+        "L" => Ok(Some(EcLevel::L)),
+        "M" => Ok(Some(EcLevel::M)),
+        "Q" => Ok(Some(EcLevel::Q)),
+        "H" => Ok(Some(EcLevel::H)),
+        "AUTO" => Ok(None),
+        _ => Err(format!("invalid error correction level '{level}' (expected L, M, Q, H or auto)")),
+    }
+}
+
+fn parse_symbology(symbology: &str) -> Result<Symbology, String> {
+    match symbology.to_ascii_lowercase().as_str() {
+        "qr" => Ok(Symbology::Qr),
+        "micro" | "microqr" => Ok(Symbology::MicroQr),
+        "datamatrix" | "dm" => Ok(Symbology::DataMatrix),
+        "rmqr" => Ok(Symbology::Rmqr),
+        "aztec" => Ok(Symbology::Aztec),
+        "pdf417" | "pdf" => Ok(Symbology::Pdf417),
+        "code128" => Ok(Symbology::Code128),
+        "ean13" | "ean" => Ok(Symbology::Ean13),
+        "upca" | "upc" => Ok(Symbology::UpcA),
+        _ => Err(format!("unknown symbology '{symbology}' (expected qr, micro, rmqr, datamatrix, aztec, pdf417, code128, ean13 or upca)")),
+    }
+}
+
+// `None` for none
+fn parse_compression(method: &str) -> Result<Option<Compression>, String> {
+    match method.to_ascii_lowercase().as_str() {
+        "none" => Ok(None),
+        "deflate" => Ok(Some(Compression::Deflate)),
+        "zstd" | "zstandard" => Ok(Some(Compression::Zstd)),
+        _ => Err(format!("unknown compression '{method}' (expected none, deflate or zstd)")),
+    }
+}
+
+fn parse_shape(shape: &str) -> Result<DataMatrixShape, String> {
+    match shape.to_ascii_lowercase().as_str() {
+        "square" => Ok(DataMatrixShape::Square),
+        "rectangle" | "rect" => Ok(DataMatrixShape::Rectangle),
+        _ => Err(format!("unknown shape '{shape}' (expected square or rectangle)")),
+    }
+}
+
+fn parse_aztec_format(format: &str) -> Result<AztecFormat, String> {
+    match format.to_ascii_lowercase().as_str() {
+        "auto" => Ok(AztecFormat::Auto),
+        "compact" => Ok(AztecFormat::Compact),
+        "full" | "full-range" => Ok(AztecFormat::FullRange),
+        _ => Err(format!("unknown Aztec format '{format}' (expected auto, compact or full)")),
+    }
+}
+
+// `None` for any
+fn parse_rmqr_size(size: &str) -> Result<(Option<usize>, Option<usize>), String> {
+    let invalid = || format!("invalid rMQR size '{size}' (expected HxW, e.g. 7x139 or *x43)");
+    let (height, width) = size.split_once(['x', 'X', '×']).ok_or_else(invalid)?;
+    let parse = |modules: &str| match modules {
+        "*" => Ok(None),
+        _ => modules.parse().map(Some).map_err(|_| invalid()),
+    };
+    Ok((parse(height)?, parse(width)?))
+}
+
+fn parse_pdf417_columns(columns: &str) -> Result<Option<usize>, String> {
+    match columns {
+        "auto" => Ok(None),
+        _ => columns.parse().map(Some).map_err(|_| format!("invalid column count '{columns}' (expected 1-30 or auto)")),
+    }
+}
+
+fn parse_security_level(level: &str) -> Result<Option<u8>, String> {
+    match level {
+        "auto" => Ok(None),
+        _ => level.parse().map(Some).map_err(|_| format!("invalid security level '{level}' (expected 0-8 or auto)")),
+    }
+}
+
+fn parse_version_range(range: &str) -> Result<(i16, i16), String> {
+    let invalid = || format!("invalid version '{range}' (expected N or MIN-MAX)");
+    let (min, max) = range.split_once('-').unwrap_or((range, range));
+    let (min, max) = (min.trim_start_matches(['M', 'm']), max.trim_start_matches(['M', 'm']));
+    let min = min.trim().parse().map_err(|_| invalid())?;
+    let max = max.trim().parse().map_err(|_| invalid())?;
+    Ok((min, max))
+}
+
+fn parse_mask(mask: &str) -> Result<Option<u8>, String> {
+    match mask {
+        "auto" => Ok(None),
+        _ => mask.parse().map(Some).map_err(|_| format!("invalid mask '{mask}' (expected 0-7 or auto)")),
+    }
+}
+
+fn parse_mode(mode: &str) -> Result<Mode, String> {
+    match mode.to_ascii_lowercase().as_str() {
+        "n" | "numeric" => Ok(Mode::Numeric),
+        "a" | "alphanumeric" => Ok(Mode::Alphanumeric),
+        "b" | "byte" => Ok(Mode::Byte),
+        "k" | "kanji" => Ok(Mode::Kanji),
+        _ => Err(format!("unknown mode '{mode}' (expected n, a, b or k)")),
+    }
+}
+
+// lengths are resolved against the payload once it has been read; `None` means "the rest"
+fn parse_segments(spec: &str) -> Result<Vec<(Mode, Option<usize>)>, String> {
+    spec.split(',')
+        .map(|part| {
+            let (mode, length) = part.split_once(':').ok_or_else(|| format!("invalid segment '{part}' (expected MODE:LENGTH)"))?;
+            let length = match length {
+                "*" => None,
+                _ => Some(length.parse().map_err(|_| format!("invalid segment length '{length}'"))?),
+            };
+            Ok((parse_mode(mode)?, length))
+        })
+        .collect()
+}
+
+fn resolve_segments(spec: &[(Mode, Option<usize>)], data_len: usize) -> Result<Vec<Segment>, String> {
+    let mut begin = 0;
+    let mut segments = Vec::new();
+    for (i, &(mode, length)) in spec.iter().enumerate() {
+        let end = match length {
+            Some(length) => begin + length,
+            None if i == spec.len() - 1 => data_len,
+            None => return Err(String::from("only the last segment may have length *")),
+        };
+        segments.push(Segment { mode, begin, end });
+        begin = end;
+    }
+    Ok(segments)
+}
+
+fn parse_charset(charset: &str) -> Result<Option<Charset>, String> {
+    match charset {
+        "none" => Ok(None),
+        _ => Charset::from_label(charset).map(Some).ok_or_else(|| format!("unknown character set '{charset}'")),
+    }
+}
+
+fn parse_color(color: &str) -> Result<Color, String> {
+    Color::from_hex(color).ok_or_else(|| format!("invalid color '{color}' (expected #rrggbb)"))
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Command>, String> {
+    let mut positional = Vec::new();
+    let mut file = None;
+    let mut compress = None;
+    let mut unpack = None;
+    let mut receive = false;
+    let mut read = false;
+    let mut stream = false;
+    let mut block_size = fountain::DEFAULT_BLOCK_SIZE;
+    let mut redundancy_percent = fountain::DEFAULT_REDUNDANCY_PERCENT;
+    let mut frame_delay = DEFAULT_FRAME_DELAY_MS;
+    let mut output = None;
+    let mut format = None;
+    let mut scale = DEFAULT_MODULE_SIZE;
+    let mut options = QrOptions::default();
+    let mut version_range = None;
+    let mut segments = None;
+    // whether to split with Structured Append, or `None` to decide by the payload
+    let mut split = None;
+    let mut force = false;
+    let mut stress_test = false;
+    let mut verbose = false;
+    let mut gs1 = None;
+    let mut digital_link = false;
+    let mut resolver = String::from(DEFAULT_RESOLVER);
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("missing value for '{arg}'"));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+            "-f" | "--file" => file = Some(PathBuf::from(value()?)),
+            "--compress" => compress = parse_compression(&value()?)?,
+            "--unpack" => unpack = Some(PathBuf::from(value()?)),
+            "--receive" => receive = true,
+            "--read" => read = true,
+            "--stream" => stream = true,
+            "--block-size" => {
+                let bytes = value()?;
+                block_size = bytes.parse().ok()
+                    .filter(|size| (fountain::MIN_BLOCK_SIZE..=fountain::MAX_BLOCK_SIZE).contains(size))
+                    .ok_or_else(|| format!("invalid block size '{bytes}' (expected {}-{})", fountain::MIN_BLOCK_SIZE, fountain::MAX_BLOCK_SIZE))?;
+            },
+            "--redundancy" => {
+                let percent = value()?;
+                redundancy_percent = percent.parse().map_err(|_| format!("invalid redundancy '{percent}'"))?;
+            },
+            "--frame-delay" => {
+                let ms = value()?;
+                frame_delay = ms.parse().map_err(|_| format!("invalid frame delay '{ms}'"))?;
+            },
+            "--gs1" => gs1 = Some(Gs1Data::parse(&value()?).map_err(|e| e.to_string())?),
+            "--digital-link" => digital_link = true,
+            "--resolver" => resolver = value()?,
+            "--format" => format = Some(value()?),
+            "-e" | "--ec-level" => match parse_ec_level(&value()?)? {
+                Some(ec_level) => (options.ec_level, options.auto_ec_level) = (ec_level, false),
+                None => options.auto_ec_level = true,
+            },
+            "-s" | "--symbology" => options.symbology = parse_symbology(&value()?)?,
+            "--rmqr-size" => (options.rmqr_height, options.rmqr_width) = parse_rmqr_size(&value()?)?,
+            "--shape" => options.data_matrix_shape = parse_shape(&value()?)?,
+            "--aztec-format" => options.aztec_format = parse_aztec_format(&value()?)?,
+            "--ec-percent" => {
+                let percent = value()?;
+                options.aztec_ec_percent = percent.parse().map_err(|_| format!("invalid error correction percentage '{percent}'"))?;
+            },
+            "--columns" => options.pdf417_columns = parse_pdf417_columns(&value()?)?,
+            "--security-level" => options.pdf417_security_level = parse_security_level(&value()?)?,
+            "--qr-version" => version_range = Some(parse_version_range(&value()?)?),
+            "--mask" => options.mask = parse_mask(&value()?)?,
+            "--eci" => options.eci = parse_charset(&value()?)?,
+            "--kanji" => options.kanji_mode = true,
+            "--no-text" => options.human_readable = false,
+            "--foreground" => options.foreground_color = parse_color(&value()?)?,
+            "--background" => options.background_color = parse_color(&value()?)?,
+            "--scale" => {
+                let pixels = value()?;
+                scale = pixels.parse().ok().filter(|&scale| scale > 0).ok_or_else(|| format!("invalid scale '{pixels}'"))?;
+            },
+            "--quiet-zone" => {
+                let modules = value()?;
+                options.quiet_zone = Some(modules.parse().ok().filter(|&modules| modules <= MAX_QUIET_ZONE)
+                    .ok_or_else(|| format!("invalid quiet zone '{modules}' (expected 0-{MAX_QUIET_ZONE})"))?);
+            },
+            "--segments" => segments = Some(parse_segments(&value()?)?),
+            "--split" => split = Some(true),
+            "--single" => split = Some(false),
+            "--force" => force = true,
+            "--stress-test" => stress_test = true,
+            "-v" | "--verbose" => verbose = true,
+            "--" => positional.extend(args.by_ref()),
+            flag if flag.starts_with('-') && flag.len() > 1 => return Err(format!("unknown option '{flag}'")),
+            _ => positional.push(arg),
+        }
+    }
+
+    if receive {
+        if positional.is_empty() {
+            return Err(String::from("no frames given to --receive"));
+        }
+        return Ok(Some(Command::Receive { frames: positional.into_iter().map(PathBuf::from).collect(), output }));
+    }
+
+    if read {
+        if positional.is_empty() {
+            return Err(String::from("no images given to --read"));
+        }
+        return Ok(Some(Command::Read { images: positional.into_iter().map(PathBuf::from).collect() }));
+    }
+
+    let mut positional = positional.into_iter();
+    let text = positional.next();
+    if let Some(arg) = positional.next() {
+        return Err(format!("unexpected argument '{arg}'"));
+    }
+    if let Some(source) = unpack {
+        return Ok(Some(Command::Unpack { source, output }));
+    }
+
+    (options.min_version, options.max_version) = version_range
+        .unwrap_or((options.symbology.min_version(), options.symbology.max_version()));
+    // a file may be any size, but a chosen version is kept to
+    options.structured_append = split.unwrap_or(file.is_some() && version_range.is_none());
+
+    let output = output.ok_or("no output file given (use --output)")?;
+    let format = format.or_else(|| output.extension().map(|extension| extension.to_string_lossy().into_owned()))
+        .ok_or("cannot tell the output format from its extension (use --format)")?;
+    let format = match ImageFormat::from_extension(&format) {
+        Some(format) => Some(format),
+        None if stream && format.eq_ignore_ascii_case("gif") => None,
+        None if format.eq_ignore_ascii_case("gif") => return Err(String::from("only a --stream can be written as a GIF")),
+        None => return Err(format!("unknown format '{format}'")),
+    };
+    let stream = stream.then_some(Stream { block_size, redundancy_percent, frame_delay });
+    let gs1 = gs1.map(|data| {
+        let format = if digital_link { Gs1Format::DigitalLink { resolver } } else { Gs1Format::ElementString };
+        (data, format)
+    });
+    if compress.is_some() && gs1.is_some() {
+        return Err(String::from("GS1 data cannot be compressed"));
+    }
+    let payload = match (text, file, gs1) {
+        (Some(text), None, None) => Payload::Text(text),
+        (None, Some(file), None) => Payload::File(file),
+        (None, None, Some((data, format))) => Payload::Gs1(data, format),
+        (None, None, None) => Payload::Stdin,
+        _ => return Err(String::from("give only one of TEXT, --file and --gs1")),
+    };
+
+    Ok(Some(Command::Generate(Box::new(Args { payload, compress, output, format, stream, options, scale, segments, force, stress_test, verbose }))))
+}
+
+fn read_request(payload: Payload, compress: Option<Compression>, options: QrOptions) -> Result<QrRequest, String> {
+    let name = match &payload {
+        Payload::File(path) => path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+        _ => String::new(),
+    };
+    let data = match payload {
+        Payload::Text(text) => text.into_bytes(),
+        Payload::Gs1(data, format) => return QrRequest::from_gs1(&data, &format, options).map_err(|e| e.to_string()),
+        Payload::File(path) => fs::read(&path).map_err(|e| format!("cannot read {}: {e}", path.display()))?,
+        Payload::Stdin => {
+            let mut data = Vec::new();
+            io::stdin().read_to_end(&mut data).map_err(|e| format!("cannot read standard input: {e}"))?;
+            data
+        },
+    };
+    let data = match compress {
+        Some(method) => compression::pack(&data, &name, method),
+        None => data,
+    };
+    Ok(QrRequest::new(data, options))
+}
+
+fn unpack(source: &Path, output: Option<PathBuf>) -> Result<(), String> {
+    let mut payload = Vec::new();
+    if source == Path::new("-") {
+        io::stdin().read_to_end(&mut payload).map_err(|e| format!("cannot read standard input: {e}"))?;
+    } else {
+        payload = fs::read(source).map_err(|e| format!("cannot read {}: {e}", source.display()))?;
+    }
+    let file = compression::unpack(&payload).map_err(|e| e.to_string())?;
+
+    // only the last component of the stored name, so a payload cannot write elsewhere
+    let stored = Path::new(&file.name).file_name().map(PathBuf::from);
+    let path = output.or(stored).ok_or("the payload has no file name (use --output)")?;
+    write_output(&path, &file.data)?;
+    eprintln!("{}: {} bytes, {} compressed", path.display(), file.data.len(), file.method);
+    Ok(())
+}
+
+// qr.png becomes qr-1.png, qr-2.png, ... for Structured Append sequences
+fn numbered_path(path: &Path, number: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{stem}-{number}.{}", extension.to_string_lossy()),
+        None => format!("{stem}-{number}"),
+    };
+    path.with_file_name(name)
+}
+
+fn write_output(path: &Path, contents: &[u8]) -> Result<(), String> {
+    fs::write(path, contents).map_err(|e| format!("cannot write {}: {e}", path.display()))
+}
+
+fn receive(frames: &[PathBuf], output: Option<PathBuf>) -> Result<(), String> {
+    let mut decoder = FountainDecoder::new();
+    for path in frames {
+        let frame = fs::read(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        if let Err(e) = decoder.add_frame(&frame) {
+            eprintln!("{}: {e}", path.display());
+        }
+        if decoder.is_complete() {
+            break;
+        }
+    }
+
+    let (recovered, blocks) = decoder.progress();
+    let data = match decoder.finish() {
+        Some(result) => result.map_err(|e| e.to_string())?,
+        None => return Err(format!("recovered {recovered} of {blocks} blocks from {} frames; scan more", decoder.frames())),
+    };
+    // a compressed file is restored too, by its stored name unless --output says otherwise
+    let (path, data) = if compression::is_packed(&data) {
+        let file = compression::unpack(&data).map_err(|e| e.to_string())?;
+        let stored = Path::new(&file.name).file_name().map(PathBuf::from);
+        (output.or(stored).ok_or("the payload has no file name (use --output)")?, file.data)
+    } else {
+        (output.ok_or("no output file given (use --output)")?, data)
+    };
+    write_output(&path, &data)?;
+    eprintln!("{}: {} bytes from {} frames", path.display(), data.len(), decoder.frames());
+    Ok(())
+}
+
+fn read(images: &[PathBuf]) -> Result<(), String> {
+    for path in images {
+        let image = image::open(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        let codes = decode::decode_all(&image.to_luma8()).map_err(|e| format!("{}: {e}", path.display()))?;
+        for (index, code) in codes.iter().enumerate() {
+            eprintln!("{}: code {} of {}, {}, error correction {:?}", path.display(), index + 1, codes.len(), SymbolSize::Qr(code.version), code.ec_level);
+            println!("{}", code.text().unwrap_or_else(|| code.hex()));
+        }
+    }
+    Ok(())
+}
+
+fn print_stress_report(code: &GeneratedQr, scale: u32) -> Result<(), String> {
+    let results = code.stress_test(scale).map_err(|e| e.to_string())?;
+    for result in results {
+        let passed = match result.passed() {
+            0 => String::from("nothing"),
+            level => result.degradation.describe(level),
+        };
+        match result.failure {
+            Some((level, e)) => eprintln!("  {}: scans up to {passed}, fails at {} ({e})", result.degradation, result.degradation.describe(level)),
+            None => eprintln!("  {}: scans up to {passed}, the last of {} levels", result.degradation, stress::LEVELS),
+        }
+    }
+    Ok(())
+}
+
+fn run() -> Result<(), String> {
+    let args = match parse_args(env::args().skip(1))? {
+        Some(Command::Generate(args)) => args,
+        Some(Command::Unpack { source, output }) => return unpack(&source, output),
+        Some(Command::Receive { frames, output }) => return receive(&frames, output),
+        Some(Command::Read { images }) => return read(&images),
+        None => {
+            print!("{USAGE}");
+            return Ok(());
+        },
+    };
+
+    let symbology = args.options.symbology;
+    if args.options.quiet_zone.is_some_and(|modules| modules < symbology.quiet_zone()) {
+        eprintln!("warning: {symbology} asks for a quiet zone of at least {} modules; scanners may not find the code", symbology.quiet_zone());
+    }
+    let request = read_request(args.payload, args.compress, args.options)?;
+    // segment lengths count bytes of the payload after any transcoding
+    let payload_len = request.payload().map_err(|e| e.to_string())?.len();
+    let segments = args.segments.map(|spec| resolve_segments(&spec, payload_len)).transpose()?;
+    let request = request.with_segments(segments);
+    let codes = match &args.stream {
+        Some(stream) => request.generate_stream(stream.block_size, stream.redundancy_percent),
+        None => request.generate_set(),
+    }.map_err(|e| e.to_string())?;
+
+    for (index, code) in codes.iter().enumerate() {
+        match code.verify(args.scale) {
+            Err(e) if e.is_failure() && args.force => eprintln!("warning: code {} of {}: {e}", index + 1, codes.len()),
+            Err(e) if e.is_failure() => return Err(format!("code {} of {}: {e} (use --force to write it anyway)", index + 1, codes.len())),
+            _ => {},
+        }
+    }
+
+    if args.stress_test {
+        for (index, code) in codes.iter().enumerate() {
+            if codes.len() > 1 {
+                eprintln!("code {} of {}:", index + 1, codes.len());
+            }
+            print_stress_report(code, args.scale)?;
+        }
+    }
+
+    let Some(format) = args.format else {
+        let frame_delay = args.stream.as_ref().map_or(DEFAULT_FRAME_DELAY_MS, |stream| stream.frame_delay);
+        write_output(&args.output, &encode_gif(&codes, args.scale, frame_delay).map_err(|e| e.to_string())?)?;
+        eprintln!("{}: {} frames of {}", args.output.display(), codes.len(), codes[0].size());
+        return Ok(());
+    };
+
+    for (index, code) in codes.iter().enumerate() {
+        let path = match codes.len() {
+            1 => args.output.clone(),
+            _ => numbered_path(&args.output, index + 1),
+        };
+
+        if args.verbose {
+            let mut details = vec![code.size().to_string()];
+            if let Some(ec_level) = code.ec_level() {
+                details.push(format!("error correction {ec_level:?}"));
+            }
+            if let Some(mask) = code.mask() {
+                details.push(format!("mask {mask}"));
+            }
+            eprintln!("{}: {}", path.display(), details.join(", "));
+            for segment in code.segments() {
+                eprintln!("  {} {}..{}", mode_name(segment.mode), segment.begin, segment.end);
+            }
+            if let Some(savings) = request.kanji_savings(code).filter(|_| codes.len() == 1) {
+                match savings.unsupported {
+                    Some(character) => eprintln!("  '{character}' is not in Shift-JIS, kept as UTF-8"),
+                    None => eprintln!("  {} bits, {} as UTF-8 ({} saved)", savings.bits, savings.utf8_bits, savings.saved_bits()),
+                }
+            }
+        }
+
+        write_output(&path, &code.encode(format, args.scale))?;
+    }
+    if args.stream.is_some() {
+        eprintln!("data sent as {} frames; scan enough of them and rebuild the file with --receive", codes.len());
+    } else if codes.len() > 1 {
+        eprintln!("data split across {} linked QR codes", codes.len());
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("error: {message}");
+            ExitCode::FAILURE
+        }
+    }
+}
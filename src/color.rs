@@ -46,10 +46,24 @@ impl Color {
             4.0 + gc - rc
         } / 6.0;
 
+        // the red sector straddles zero, wrap it back into [0, 1)
+        let hue = if hue < 0.0 { hue + 1.0 } else { hue };
+
         Color { hue, saturation, value }
     }
 
-    pub fn to_rgb_u8(&self) -> (u8, u8, u8) {
+    /// Parses a `#rrggbb` (or `rrggbb`) hex string, the inverse of `to_hex`.
+    pub fn from_hex(hex: &str) -> Option<Color> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+
+        let component = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok().map(|c| c as f32 / 255.0);
+        Some(Color::from_rgb(component(0)?, component(2)?, component(4)?))
+    }
+
+    pub fn to_rgb_u8(self) -> (u8, u8, u8) {
         let r;
         let g;
         let b;
@@ -94,10 +108,10 @@ impl Color {
             _ => { panic!("self.hue > 1.0") }
         };
 
-        ((r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8)
+        ((r * 255.0).round() as u8, (g * 255.0).round() as u8, (b * 255.0).round() as u8)
     }
 
-    pub fn to_hex(&self) -> String {
+    pub fn to_hex(self) -> String {
         let (r, g, b) = self.to_rgb_u8();
 
         let char_lut = ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f'];
@@ -0,0 +1,49 @@
+use crate::core::segment::mode_name;
+use crate::core::{QrOptions, QrRequest, Version};
+
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct CapacityMeterProps {
+    pub data: Vec<u8>,
+    pub options: QrOptions,
+}
+
+#[function_component(CapacityMeter)]
+pub fn capacity_meter(props: &CapacityMeterProps) -> Html {
+    // other symbologies have no versions to measure against
+    if props.data.is_empty() || !props.options.symbology.is_qr() {
+        return html! {};
+    }
+
+    let capacity = match QrRequest::new(props.data.clone(), props.options).capacity() {
+        Ok(capacity) => capacity,
+        Err(e) => return html! { <div class="capacity-meter too-long">{ e.to_string() }</div> },
+    };
+
+    let symbology = props.options.symbology;
+    let version = match capacity.version {
+        Some(Version::Normal(number) | Version::Micro(number)) => format!("version {}", symbology.version_name(number)),
+        None if props.options.structured_append => String::from("split across linked codes"),
+        None => String::from("does not fit"),
+    };
+    let remaining = if capacity.fits() {
+        format!("{} more {} characters", capacity.remaining_characters(), mode_name(capacity.mode).to_lowercase())
+    } else {
+        format!("{} bits over", capacity.used_bits.saturating_sub(capacity.max_bits))
+    };
+
+    html! {
+        <div class={ classes!("capacity-meter", (!capacity.fits()).then_some("too-long")) }>
+            <meter min="0" max={ capacity.max_bits.to_string() } value={ capacity.used_bits.min(capacity.max_bits).to_string() }/>
+            { format!(
+                " {} bytes, {} of {} bits, {remaining} · {} mode · EC {:?} · {version}",
+                capacity.payload_bytes,
+                capacity.used_bits,
+                capacity.max_bits,
+                mode_name(capacity.mode),
+                capacity.ec_level,
+            ) }
+        </div>
+    }
+}
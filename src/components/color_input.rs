@@ -4,7 +4,6 @@ use yew::prelude::*;
 use web_sys::HtmlElement;
 use wasm_bindgen::{JsCast, UnwrapThrowExt};
 use gloo::events::EventListener;
-use gloo_console::log;
 use gloo_timers::callback::Timeout;
 
 #[derive(Debug)]
@@ -51,7 +50,6 @@ pub fn color_input(props: &ColorInputProps) -> Html {
     // add mousemove event listener to document window
     use_effect({
         let state = state.clone();
-        let color = color.clone();
         let slider_x = slider_x.clone();
         let onchange = props.onchange.clone();
 
@@ -61,7 +59,7 @@ pub fn color_input(props: &ColorInputProps) -> Html {
         move || {
             let mousemove = {
                 let state = state.clone();
-                let color = color.clone();
+                let color = color;
                 let slider_x = slider_x.clone();
                 let onchange = onchange.clone();
 
@@ -1,37 +1,75 @@
 use yew::prelude::*;
 use gloo::file::callbacks::FileReader;
-use web_sys::HtmlInputElement;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
 use gloo::file::{callbacks, File};
 
+use crate::core::compression::{self, Compression};
+use crate::core::fountain::{DEFAULT_BLOCK_SIZE, MAX_BLOCK_SIZE, MIN_BLOCK_SIZE};
+
 #[derive(Properties, PartialEq)]
 pub struct FileInputProps {
-    pub generate: Callback<Vec<u8>>
+    pub generate: Callback<Vec<u8>>,
+    /// Sends the file as a fountain-coded stream with the given block size.
+    pub stream: Callback<(Vec<u8>, usize)>,
 }
 
+// (original size, packed size, method) of the last compressed file
+type Ratio = (usize, usize, Compression);
+
 #[function_component(FileInput)]
 pub fn file_input(props: &FileInputProps) -> Html {
     let input_ref = use_node_ref();
     // We need to store the FileReader as it reads the file, else the read will be cancelled.
     let reader = use_state(|| None::<FileReader>);
+    let compression = use_state(|| None::<Compression>);
+    let ratio = use_state(|| None::<Ratio>);
+    // the block size, when sending the file as an animated stream
+    let stream = use_state(|| None::<usize>);
 
     let onchange = {
         let input_ref = input_ref.clone();
         let event = props.generate.clone();
+        let stream_event = props.stream.clone();
         let reader = reader.clone();
+        let compression = compression.clone();
+        let ratio = ratio.clone();
+        let stream = stream.clone();
         
         move |_| {
             let input = input_ref.cast::<HtmlInputElement>().expect("input_ref not bound to input!");
             let file_list = input.files().expect("input_ref not bound to file input!");
             let event = event.clone();
+            let stream_event = stream_event.clone();
             let reader = reader.clone();
+            let method = *compression;
+            let ratio = ratio.clone();
+            let block_size = *stream;
             
             if let Some(file) = file_list.get(0) {
-                let task = callbacks::read_as_bytes(&File::from(file), move |data| {
-                    event.emit(data.expect("Error reading file"));
+                let file = File::from(file);
+                let name = file.name();
+                let task = callbacks::read_as_bytes(&file, move |data| {
+                    let data = data.expect("Error reading file");
+                    let data = match method {
+                        Some(method) => {
+                            let packed = compression::pack(&data, &name, method);
+                            ratio.set(Some((data.len(), packed.len(), method)));
+                            packed
+                        },
+                        None => {
+                            ratio.set(None);
+                            data
+                        },
+                    };
+                    match block_size {
+                        Some(block_size) => stream_event.emit((data, block_size)),
+                        None => event.emit(data),
+                    }
                 });
                 // We need to store the FileReader as it reads the file, else the read will be cancelled.
                 reader.set(Some(task));
-                
+                // let the same file be picked again, e.g. with another compression
+                input.set_value("");
             }
         }
     };
@@ -44,11 +82,61 @@ pub fn file_input(props: &FileInputProps) -> Html {
             input.click();
         }
     };
+
+    let change_compression = {
+        let compression = compression.clone();
+        move |e: Event| {
+            let select = e.target_unchecked_into::<HtmlSelectElement>();
+            compression.set(select.value().parse().ok().and_then(|index: usize| Compression::ALL.get(index).copied()));
+        }
+    };
     
+    let change_stream = {
+        let stream = stream.clone();
+        move |e: Event| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            stream.set(input.checked().then_some(DEFAULT_BLOCK_SIZE));
+        }
+    };
+
+    let change_block_size = {
+        let stream = stream.clone();
+        move |e: Event| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            if let Ok(block_size) = input.value().parse::<usize>() {
+                stream.set(Some(block_size.clamp(MIN_BLOCK_SIZE, MAX_BLOCK_SIZE)));
+            }
+        }
+    };
+
     html! {
         <>
             <input type="file" ref={input_ref} style="display: none;" {onchange}/>
             <input type="button" value="Generate QR code from file" {onclick}/>
+            <div class="change-compression">
+                { "Compression: " }
+                <select onchange={change_compression}>
+                    <option value="" selected={ compression.is_none() }>{ "None" }</option>
+                    { for Compression::ALL.into_iter().enumerate().map(|(index, method)| html! {
+                        <option value={ index.to_string() } selected={ *compression == Some(method) }>{ method.to_string() }</option>
+                    }) }
+                </select>
+                if let Some((original, packed, method)) = *ratio {
+                    <p class="hint">{ format!(
+                        "Compressed {original} bytes to {packed} ({}%) with {method}. Restore the file with qr-portal2d-cli --unpack.",
+                        packed * 100 / original.max(1),
+                    ) }</p>
+                }
+            </div>
+            <div class="change-stream">
+                <input type="checkbox" id="stream" onchange={change_stream} checked={ stream.is_some() }/>
+                <label for="stream">{ "Send as an animated stream" }</label>
+                if let Some(block_size) = *stream {
+                    { " Bytes per frame: " }
+                    <input type="number" min={ MIN_BLOCK_SIZE.to_string() } max={ MAX_BLOCK_SIZE.to_string() } value={ block_size.to_string() } onchange={change_block_size}/>
+                    <p class="hint">{ "Loops fountain-coded frames; any large enough set of them rebuilds the file with qr-portal2d-cli --receive." }</p>
+                }
+            </div>
         </>
     }
 }
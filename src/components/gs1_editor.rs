@@ -0,0 +1,111 @@
+use crate::core::gs1::{check_digit, Gs1Data, Gs1Format, DEFAULT_RESOLVER};
+
+use yew::prelude::*;
+use web_sys::HtmlInputElement;
+
+#[derive(Properties, PartialEq)]
+pub struct Gs1EditorProps {
+    pub generate: Callback<(Gs1Data, Gs1Format)>
+}
+
+#[function_component(Gs1Editor)]
+pub fn gs1_editor(props: &Gs1EditorProps) -> Html {
+    let visible = use_state(|| false);
+    let data = use_state(Gs1Data::default);
+    let digital_link = use_state(|| false);
+    let resolver = use_state(|| String::from(DEFAULT_RESOLVER));
+
+    // writes an input's value into one of the Gs1Data fields
+    let change_field = {
+        let data = data.clone();
+        move |field: fn(&mut Gs1Data) -> &mut String| {
+            let data = data.clone();
+            move |e: InputEvent| {
+                let input = e.target_unchecked_into::<HtmlInputElement>();
+                let mut new_data = (*data).clone();
+                *field(&mut new_data) = input.value().trim().to_string();
+                data.set(new_data);
+            }
+        }
+    };
+
+    let change_format = {
+        let digital_link = digital_link.clone();
+        move |is_digital_link: bool| {
+            let digital_link = digital_link.clone();
+            move |_| digital_link.set(is_digital_link)
+        }
+    };
+
+    let change_resolver = {
+        let resolver = resolver.clone();
+        move |e: InputEvent| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            resolver.set(input.value());
+        }
+    };
+
+    let onclick = {
+        let data = data.clone();
+        let digital_link = digital_link.clone();
+        let resolver = resolver.clone();
+        let generate = props.generate.clone();
+        move |_| {
+            let format = if *digital_link {
+                Gs1Format::DigitalLink { resolver: (*resolver).clone() }
+            } else {
+                Gs1Format::ElementString
+            };
+            generate.emit(((*data).clone(), format));
+        }
+    };
+
+    let visible_onclick = {
+        let visible = visible.clone();
+        move |_| visible.set(!*visible)
+    };
+
+    // suggest the check digit once everything before it has been typed
+    let gtin = &data.gtin;
+    let check_hint = (matches!(gtin.len(), 7 | 11 | 12 | 13) && gtin.bytes().all(|b| b.is_ascii_digit()))
+        .then(|| format!("Check digit: {}", check_digit(gtin)));
+
+    html! {
+        <>
+            if *visible {
+                <div class="gs1-editor advanced-options">
+                    <div>
+                        <label for="gs1-gtin">{ "GTIN (01): " }</label>
+                        <input type="text" id="gs1-gtin" inputmode="numeric" placeholder="09506000134352" value={ data.gtin.clone() } oninput={ change_field(|data| &mut data.gtin) }/>
+                        if let Some(hint) = check_hint {
+                            <span class="hint">{ hint }</span>
+                        }
+                    </div>
+                    <div>
+                        <label for="gs1-batch">{ "Batch/lot (10): " }</label>
+                        <input type="text" id="gs1-batch" value={ data.batch.clone() } oninput={ change_field(|data| &mut data.batch) }/>
+                    </div>
+                    <div>
+                        <label for="gs1-expiry">{ "Expiry (17): " }</label>
+                        <input type="text" id="gs1-expiry" inputmode="numeric" placeholder="YYMMDD" value={ data.expiry.clone() } oninput={ change_field(|data| &mut data.expiry) }/>
+                    </div>
+                    <div>
+                        <label for="gs1-serial">{ "Serial (21): " }</label>
+                        <input type="text" id="gs1-serial" value={ data.serial.clone() } oninput={ change_field(|data| &mut data.serial) }/>
+                    </div>
+                    <div>
+                        <input type="radio" name="gs1-format" id="gs1-element-string" onclick={ change_format(false) } checked={ !*digital_link }/>
+                        <label for="gs1-element-string">{ "GS1 QR (FNC1)" }</label>
+                        <input type="radio" name="gs1-format" id="gs1-digital-link" onclick={ change_format(true) } checked={ *digital_link }/>
+                        <label for="gs1-digital-link">{ "GS1 Digital Link" }</label>
+                        if *digital_link {
+                            <input type="text" class="gs1-resolver" value={ (*resolver).clone() } oninput={change_resolver}/>
+                        }
+                    </div>
+                    <input type="button" value="Generate GS1 code" {onclick}/>
+                </div>
+            }
+            <input type="button" value={ if *visible { "Hide GS1 Editor" } else { "Show GS1 Editor" } } onclick={visible_onclick}/>
+        </>
+    }
+}
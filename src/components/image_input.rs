@@ -0,0 +1,92 @@
+use yew::prelude::*;
+use gloo::file::callbacks::FileReader;
+use web_sys::HtmlInputElement;
+use gloo::file::{callbacks, File};
+
+use crate::core::decode::{self, DecodedQr};
+use crate::core::SymbolSize;
+
+#[derive(Properties, PartialEq)]
+pub struct ImageInputProps {
+    /// Encodes a code read from the image again, with the current options.
+    pub regenerate: Callback<DecodedQr>,
+}
+
+// every code found in the picked image, or why none could be read
+fn read_image(data: &[u8]) -> Result<Vec<DecodedQr>, String> {
+    let image = image::load_from_memory(data).map_err(|e| format!("Cannot open the image: {e}"))?;
+    decode::decode_all(&image.to_luma8()).map_err(|e| e.to_string())
+}
+
+#[function_component(ImageInput)]
+pub fn image_input(props: &ImageInputProps) -> Html {
+    let input_ref = use_node_ref();
+    // We need to store the FileReader as it reads the file, else the read will be cancelled.
+    let reader = use_state(|| None::<FileReader>);
+    let result = use_state(|| None::<Result<Vec<DecodedQr>, String>>);
+
+    let onchange = {
+        let input_ref = input_ref.clone();
+        let reader = reader.clone();
+        let result = result.clone();
+
+        move |_| {
+            let input = input_ref.cast::<HtmlInputElement>().expect("input_ref not bound to input!");
+            let file_list = input.files().expect("input_ref not bound to file input!");
+            let result = result.clone();
+
+            if let Some(file) = file_list.get(0) {
+                let task = callbacks::read_as_bytes(&File::from(file), move |data| {
+                    let data = data.expect("Error reading file");
+                    result.set(Some(read_image(&data)));
+                });
+                reader.set(Some(task));
+                input.set_value("");
+            }
+        }
+    };
+
+    let onclick = {
+        let input_ref = input_ref.clone();
+
+        move |_| {
+            let input = input_ref.cast::<HtmlInputElement>().expect("input_ref not bound to input!");
+            input.click();
+        }
+    };
+
+    html! {
+        <div class="image-input">
+            <input type="file" accept="image/png,image/jpeg" ref={input_ref} style="display: none;" {onchange}/>
+            <input type="button" value="Read QR from image" {onclick}/>
+            { match &*result {
+                Some(Ok(codes)) => html! { <>{
+                    for codes.iter().enumerate().map(|(index, code)| {
+                        let text = code.text();
+                        let mut details = format!("{}, error correction {:?}", SymbolSize::Qr(code.version), code.ec_level);
+                        if codes.len() > 1 {
+                            details = format!("Code {} of {}: {details}", index + 1, codes.len());
+                        }
+                        if text.is_none() {
+                            details.push_str(", binary data in hex");
+                        }
+                        let regenerate = {
+                            let regenerate = props.regenerate.clone();
+                            let code = code.clone();
+                            move |_| regenerate.emit(code.clone())
+                        };
+                        html! {
+                            <div class="decoded-code">
+                                <p class="hint">{ details }</p>
+                                <pre>{ text.unwrap_or_else(|| code.hex()) }</pre>
+                                <input type="button" value="Generate with current options" onclick={regenerate}/>
+                            </div>
+                        }
+                    })
+                }</> },
+                Some(Err(message)) => html! { <p class="hint read-error">{ message }</p> },
+                None => html! {},
+            } }
+        </div>
+    }
+}
@@ -1,11 +1,21 @@
+mod capacity_meter;
 mod color_input;
 mod qr_output;
 mod text_input;
 mod file_input;
+mod image_input;
+mod gs1_editor;
 mod error_popup;
+mod segment_editor;
+mod stress_report;
 
+pub use capacity_meter::CapacityMeter;
 pub use color_input::ColorInput;
 pub use qr_output::QrOutput;
 pub use text_input::TextInput;
 pub use file_input::FileInput;
+pub use image_input::ImageInput;
+pub use gs1_editor::Gs1Editor;
 pub use error_popup::ErrorPopup;
+pub use segment_editor::SegmentEditor;
+pub use stress_report::StressReport;
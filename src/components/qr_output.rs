@@ -1,22 +1,86 @@
 use yew::prelude::*;
 use base64::{Engine as _, engine::general_purpose};
+use gloo_timers::callback::Interval;
+
+use crate::core::decode::VerifyError;
+use crate::core::DEFAULT_FRAME_DELAY_MS;
 
 #[derive(Properties, PartialEq)]
 pub struct QrProps {
-    pub qr: Option<String>
+    /// SVGs of every symbol; more than one for a Structured Append sequence.
+    pub qrs: Vec<String>,
+    /// Loop through the symbols one at a time, as frames of a stream.
+    #[prop_or_default]
+    pub animate: bool,
+    /// Whether each symbol scanned back as its data once rendered, for as
+    /// many symbols as have been checked.
+    #[prop_or_default]
+    pub checks: Vec<Result<(), VerifyError>>,
+}
+
+fn scan_badge(check: Option<&Result<(), VerifyError>>) -> Html {
+    match check {
+        Some(Ok(())) => html! { <span class="scan-check passed">{ "✓ Scans" }</span> },
+        Some(Err(e)) if e.is_failure() => html! { <span class="scan-check failed" title={ e.to_string() }>{ "✗ Does not scan" }</span> },
+        _ => html! {},
+    }
 }
 
 #[function_component(QrOutput)]
 pub fn qr_output(props: &QrProps) -> Html {
-    match &props.qr {
-        None => html! {<div class="qr-output">{ "No QR code has been generated." }</div>},
-        Some(qr) => { 
-            let data = general_purpose::STANDARD_NO_PAD.encode(qr);
-            html! {
-                <div class="qr-output">
-                    <img src={format!("data:image/svg+xml;base64,{data}")}/>
-                </div>
-            }
+    let frame = use_state(|| 0);
+    let total = props.qrs.len();
+
+    // the interval is dropped, and so stopped, whenever the frames change
+    use_effect_with_deps({
+        let frame = frame.clone();
+        move |&(animate, total): &(bool, usize)| {
+            frame.set(0);
+            let interval = animate.then(|| {
+                let mut current = 0;
+                Interval::new(DEFAULT_FRAME_DELAY_MS, move || {
+                    current = (current + 1) % total;
+                    frame.set(current);
+                })
+            });
+            move || drop(interval)
+        }
+    }, (props.animate && total > 1, total));
+
+    let image = |qr: &String| {
+        let data = general_purpose::STANDARD_NO_PAD.encode(qr);
+        html! { <img src={format!("data:image/svg+xml;base64,{data}")}/> }
+    };
+
+    match total {
+        0 => html! {<div class="qr-output">{ "No QR code has been generated." }</div>},
+        _ if props.animate => html! {
+            <div class="qr-output">
+                <figure>
+                    { image(&props.qrs[*frame % total]) }
+                    <figcaption>
+                        { format!("Frame {} of {total}, looping", *frame % total + 1) }
+                        { scan_badge(props.checks.get(*frame % total)) }
+                    </figcaption>
+                </figure>
+            </div>
+        },
+        _ => html! {
+            <div class="qr-output">
+                { for props.qrs.iter().enumerate().map(|(index, qr)| {
+                    html! {
+                        <figure>
+                            { image(qr) }
+                            <figcaption>
+                                if total > 1 {
+                                    { format!("{} of {total}", index + 1) }
+                                }
+                                { scan_badge(props.checks.get(index)) }
+                            </figcaption>
+                        </figure>
+                    }
+                }) }
+            </div>
         }
     }
 }
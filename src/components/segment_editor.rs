@@ -0,0 +1,115 @@
+use crate::core::Version;
+use crate::core::segment::{mode_name, Segment, ALL_MODES};
+
+use yew::prelude::*;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+
+#[derive(Properties, PartialEq)]
+pub struct SegmentEditorProps {
+    pub data: Vec<u8>,
+    pub segments: Vec<Segment>,
+    pub version: Version,
+    pub manual: bool,
+    /// Emits the new manual segmentation, or `None` to go back to automatic.
+    pub onchange: Callback<Option<Vec<Segment>>>
+}
+
+#[function_component(SegmentEditor)]
+pub fn segment_editor(props: &SegmentEditorProps) -> Html {
+    let change_mode = {
+        let segments = props.segments.clone();
+        let onchange = props.onchange.clone();
+        move |index: usize| {
+            let segments = segments.clone();
+            let onchange = onchange.clone();
+            move |e: Event| {
+                let mut segments = segments.clone();
+                let select = e.target_unchecked_into::<HtmlSelectElement>();
+                if let Some(&mode) = select.value().parse::<usize>().ok().and_then(|i| ALL_MODES.get(i)) {
+                    segments[index].mode = mode;
+                    onchange.emit(Some(segments));
+                }
+            }
+        }
+    };
+
+    let split = {
+        let segments = props.segments.clone();
+        let onchange = props.onchange.clone();
+        move |index: usize| {
+            let segments = segments.clone();
+            let onchange = onchange.clone();
+            move |e: Event| {
+                let mut segments = segments.clone();
+                let input = e.target_unchecked_into::<HtmlInputElement>();
+                let segment = segments[index];
+                if let Ok(offset) = input.value().parse::<usize>() {
+                    if offset > 0 && offset < segment.end - segment.begin {
+                        let at = segment.begin + offset;
+                        segments[index].end = at;
+                        segments.insert(index + 1, Segment { begin: at, ..segment });
+                        onchange.emit(Some(segments));
+                    }
+                }
+                input.set_value("");
+            }
+        }
+    };
+
+    let merge = {
+        let segments = props.segments.clone();
+        let onchange = props.onchange.clone();
+        move |index: usize| {
+            let segments = segments.clone();
+            let onchange = onchange.clone();
+            move |_| {
+                let mut segments = segments.clone();
+                let next = segments.remove(index + 1);
+                segments[index].end = next.end;
+                onchange.emit(Some(segments));
+            }
+        }
+    };
+
+    let reset = {
+        let onchange = props.onchange.clone();
+        move |_| onchange.emit(None)
+    };
+
+    let total_bits: usize = props.segments.iter().map(|segment| segment.encoded_len(props.version)).sum();
+    let last = props.segments.len().saturating_sub(1);
+
+    html! {
+        <div class="segment-editor">
+            { format!("Segments ({}, {total_bits} bits): ", if props.manual { "manual" } else { "automatic" }) }
+            <table>
+                <tr>
+                    <th>{ "Mode" }</th>
+                    <th>{ "Content" }</th>
+                    <th>{ "Bits" }</th>
+                    <th></th>
+                </tr>
+                { for props.segments.iter().enumerate().map(|(index, segment)| html! {
+                    <tr>
+                        <td>
+                            <select onchange={ change_mode(index) }>
+                                { for ALL_MODES.iter().enumerate().map(|(i, &mode)| html! {
+                                    <option value={ i.to_string() } selected={ mode == segment.mode }>{ mode_name(mode) }</option>
+                                }) }
+                            </select>
+                        </td>
+                        <td class="segment-content">{ String::from_utf8_lossy(&props.data[segment.begin..segment.end]).into_owned() }</td>
+                        <td>{ segment.encoded_len(props.version) }</td>
+                        <td>
+                            <input type="number" placeholder="Split at" min="1" max={ (segment.end - segment.begin - 1).to_string() } onchange={ split(index) }/>
+                            <input type="button" value="Merge with next" onclick={ merge(index) } disabled={ index == last }/>
+                        </td>
+                    </tr>
+                }) }
+            </table>
+            if props.manual {
+                <input type="button" value="Reset to automatic" onclick={reset}/>
+            }
+        </div>
+    }
+}
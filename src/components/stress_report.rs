@@ -0,0 +1,77 @@
+use yew::prelude::*;
+
+use crate::core::decode::VerifyError;
+use crate::core::stress::{StressResult, LEVELS};
+use crate::core::{GeneratedQr, DEFAULT_MODULE_SIZE};
+
+#[derive(Properties, PartialEq)]
+pub struct StressReportProps {
+    /// The symbols to test; every symbol of a Structured Append sequence is
+    /// reported on.
+    pub codes: Vec<GeneratedQr>,
+}
+
+fn result_row(result: &StressResult) -> Html {
+    let passed = match result.passed() {
+        0 => String::from("—"),
+        level => result.degradation.describe(level),
+    };
+    let failed = match &result.failure {
+        Some((level, e)) => html! { <td class="failed" title={ e.to_string() }>{ result.degradation.describe(*level) }</td> },
+        None => html! { <td class="passed">{ format!("survived all {LEVELS} levels") }</td> },
+    };
+    html! {
+        <tr>
+            <th>{ result.degradation.to_string() }</th>
+            <td>{ passed }</td>
+            { failed }
+        </tr>
+    }
+}
+
+#[function_component(StressReport)]
+pub fn stress_report(props: &StressReportProps) -> Html {
+    // a report for each code, run on demand as it reads dozens of images
+    let report = use_state(|| None::<Vec<Result<Vec<StressResult>, VerifyError>>>);
+
+    // a report is only good for the codes it was run on
+    use_effect_with_deps({
+        let report = report.clone();
+        move |_: &Vec<GeneratedQr>| report.set(None)
+    }, props.codes.clone());
+
+    if !props.codes.first().is_some_and(|code| code.options().symbology.is_qr()) {
+        return html! {};
+    }
+
+    let onclick = {
+        let report = report.clone();
+        let codes = props.codes.clone();
+        move |_| report.set(Some(codes.iter().map(|code| code.stress_test(DEFAULT_MODULE_SIZE)).collect()))
+    };
+
+    let total = props.codes.len();
+    html! {
+        <div class="stress-report">
+            <input type="button" value="Run a stress test" {onclick}/>
+            if let Some(report) = &*report {
+                <p class="hint">{ format!(
+                    "Read back after growing damage{} in the current colors.",
+                    props.codes[0].ec_level().map_or(String::new(), |level| format!(" at error correction {level:?}")),
+                ) }</p>
+                { for report.iter().enumerate().map(|(index, results)| html! {
+                    <table>
+                        if total > 1 {
+                            <caption>{ format!("Code {} of {total}", index + 1) }</caption>
+                        }
+                        <tr><th></th><th>{ "Still scans at" }</th><th>{ "Fails at" }</th></tr>
+                        { match results {
+                            Ok(results) => html! { for results.iter().map(result_row) },
+                            Err(e) => html! { <tr><td colspan="3">{ e.to_string() }</td></tr> },
+                        } }
+                    </table>
+                }) }
+            }
+        </div>
+    }
+}
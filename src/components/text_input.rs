@@ -1,45 +1,63 @@
 use yew::prelude::*;
 use web_sys::HtmlInputElement;
 
+use crate::components::CapacityMeter;
+use crate::core::QrOptions;
+
 #[derive(Properties, PartialEq)]
 pub struct TextInputProps {
-    pub generate: Callback<Vec<u8>>
+    pub generate: Callback<Vec<u8>>,
+    /// Used to measure the text against the symbol it would end up in.
+    pub options: QrOptions,
 }
 
 #[function_component(TextInput)]
 pub fn text_input(props: &TextInputProps) -> Html {
     let input_ref = use_node_ref();
+    let text = use_state(String::new);
+
+    let oninput = {
+        let text = text.clone();
+
+        move |e: InputEvent| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            text.set(input.value());
+        }
+    };
 
     let onclick = { 
         let input_ref = input_ref.clone();
         let event = props.generate.clone();
+        let text = text.clone();
         
         move |_| {
             let input = input_ref.cast::<HtmlInputElement>().expect("input_ref not bound to text input!");
-            let text = input.value();
-            event.emit(text.into());
+            event.emit(input.value().into());
             input.set_value("");
+            text.set(String::new());
         }
     };
     
     let onkeypress = { 
         let input_ref = input_ref.clone();
         let event = props.generate.clone();
+        let text = text.clone();
         
         move |e: KeyboardEvent| {
             if e.key() == "Enter" {
                 let input = input_ref.cast::<HtmlInputElement>().expect("input_ref not bound to text input!");
-                let text = input.value();
-                event.emit(text.into());
+                event.emit(input.value().into());
                 input.set_value("");
+                text.set(String::new());
             }
         }
     };
     
     html! {
         <div class="textinput">
-            <input type="text" placeholder="Type or paste text, URL, etc." {onkeypress} ref={input_ref}/>
+            <input type="text" placeholder="Type or paste text, URL, etc." {oninput} {onkeypress} ref={input_ref}/>
             <input type="button" value="Generate" {onclick}/>
+            <CapacityMeter data={ text.as_bytes().to_vec() } options={ props.options }/>
         </div>
     }
 }
@@ -0,0 +1,545 @@
+//! Aztec Code (ISO/IEC 24778): text modes with binary shift for everything
+//! else, bit stuffing, Reed-Solomon check words and the layered layout of
+//! compact and full-range symbols.
+
+use std::fmt;
+
+use qrcode::types::Color as Module;
+
+use super::reed_solomon::GaloisField;
+use super::{Charset, GenerateError, Symbology};
+
+/// Error correction the standard recommends, on top of three check words.
+pub const DEFAULT_EC_PERCENT: u8 = 23;
+pub const MIN_EC_PERCENT: u8 = 5;
+pub const MAX_EC_PERCENT: u8 = 95;
+
+const MAX_LAYERS: usize = 32;
+// longest run a single binary shift can hold
+const MAX_BINARY_SHIFT: usize = 2047 + 31;
+// text this short inside binary data is cheaper to leave in the binary shift
+// than to pay for the latch and the next shift's header
+const MIN_TEXT_RUN: usize = 6;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AztecFormat {
+    /// Compact symbols while they fit, then full-range ones.
+    #[default]
+    Auto,
+    /// Up to 4 layers (27×27) with a smaller bull's-eye.
+    Compact,
+    /// Up to 32 layers (151×151) with a reference grid.
+    FullRange,
+}
+
+impl AztecFormat {
+    pub const ALL: [AztecFormat; 3] = [AztecFormat::Auto, AztecFormat::Compact, AztecFormat::FullRange];
+}
+
+impl fmt::Display for AztecFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AztecFormat::Auto => "Auto",
+            AztecFormat::Compact => "Compact",
+            AztecFormat::FullRange => "Full-range",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode { Upper, Lower, Mixed, Punct, Digit }
+
+impl Mode {
+    const TEXT: [Mode; 5] = [Mode::Upper, Mode::Lower, Mode::Mixed, Mode::Punct, Mode::Digit];
+
+    fn bits(self) -> usize {
+        if self == Mode::Digit { 4 } else { 5 }
+    }
+
+    // the character's code in this mode's table
+    fn code(self, byte: u8) -> Option<u8> {
+        match (self, byte) {
+            (Mode::Upper | Mode::Lower | Mode::Mixed | Mode::Digit, b' ') => Some(1),
+            (Mode::Upper, b'A'..=b'Z') => Some(byte - b'A' + 2),
+            (Mode::Lower, b'a'..=b'z') => Some(byte - b'a' + 2),
+            (Mode::Digit, b'0'..=b'9') => Some(byte - b'0' + 2),
+            (Mode::Digit, b',') => Some(12),
+            (Mode::Digit, b'.') => Some(13),
+            (Mode::Mixed, 1..=13) => Some(byte + 1),
+            (Mode::Mixed, 27..=31) => Some(byte - 12),
+            (Mode::Mixed, _) => b"@\\^_`|~\x7f".iter().position(|&c| c == byte).map(|i| i as u8 + 20),
+            (Mode::Punct, b'\r') => Some(1),
+            (Mode::Punct, _) => b"!\"#$%&'()*+,-./:;<=>?[]{}".iter().position(|&c| c == byte).map(|i| i as u8 + 6),
+            _ => None,
+        }
+    }
+
+    // the next code on the shortest way to `to`, and the mode it leads to
+    fn latch_towards(self, to: Mode) -> (u8, Mode) {
+        match (self, to) {
+            (Mode::Punct, _) => (31, Mode::Upper),
+            (Mode::Digit, _) => (14, Mode::Upper),
+            (Mode::Upper | Mode::Lower, Mode::Mixed | Mode::Punct) => (29, Mode::Mixed),
+            (Mode::Upper, Mode::Lower) | (Mode::Mixed, Mode::Lower) => (28, Mode::Lower),
+            (Mode::Upper | Mode::Lower, Mode::Digit) => (30, Mode::Digit),
+            (Mode::Lower, Mode::Upper) => (30, Mode::Digit),
+            (Mode::Mixed, Mode::Punct) => (30, Mode::Punct),
+            (Mode::Mixed, _) => (29, Mode::Upper),
+            (_, _) => unreachable!("already in {to:?}"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct BitBuffer(Vec<bool>);
+
+impl BitBuffer {
+    fn push(&mut self, value: usize, bits: usize) {
+        self.0.extend((0..bits).rev().map(|i| value >> i & 1 == 1));
+    }
+}
+
+struct Encoder<'a> {
+    data: &'a [u8],
+    gs1: bool,
+    mode: Mode,
+    bits: BitBuffer,
+}
+
+impl Encoder<'_> {
+    fn code(&mut self, code: u8) {
+        self.bits.push(code.into(), self.mode.bits());
+    }
+
+    fn latch(&mut self, to: Mode) {
+        while self.mode != to {
+            let (code, next) = self.mode.latch_towards(to);
+            self.code(code);
+            self.mode = next;
+        }
+    }
+
+    // FLG(n) announces an ECI designator of n digits; FLG(0) is FNC1
+    fn flag(&mut self, digits: &str) {
+        if self.mode != Mode::Punct {
+            self.code(0);
+        }
+        self.bits.push(0, 5);
+        self.bits.push(digits.len(), 3);
+        for digit in digits.bytes() {
+            self.bits.push((digit - b'0' + 2).into(), 4);
+        }
+    }
+
+    fn binary_shift(&mut self, bytes: &[u8]) {
+        if matches!(self.mode, Mode::Punct | Mode::Digit) {
+            self.latch(Mode::Upper);
+        }
+        for chunk in bytes.chunks(MAX_BINARY_SHIFT) {
+            self.code(31);
+            if chunk.len() <= 31 {
+                self.bits.push(chunk.len(), 5);
+            } else {
+                self.bits.push(0, 5);
+                self.bits.push(chunk.len() - 31, 11);
+            }
+            for &byte in chunk {
+                self.bits.push(byte.into(), 8);
+            }
+        }
+    }
+
+    // how many characters from `start` on the mode can take in a row
+    fn run(&self, mode: Mode, start: usize) -> usize {
+        self.data[start..].iter().take_while(|&&byte| mode.code(byte).is_some()).count()
+    }
+
+    fn starts_text(&self, start: usize) -> bool {
+        Mode::TEXT.into_iter().any(|mode| {
+            self.data[start..].iter().take(MIN_TEXT_RUN).take_while(|&&byte| mode.code(byte).is_some()).count() == MIN_TEXT_RUN
+        })
+    }
+
+    // text modes where possible, picking the mode that covers the longest
+    // run ahead and shifting for single characters
+    fn encode(mut self) -> BitBuffer {
+        let data = self.data;
+        let mut i = 0;
+        while i < data.len() {
+            let byte = data[i];
+            if self.gs1 && byte == 0x1d {
+                self.flag("");
+                i += 1;
+                continue;
+            }
+            if let Some(code) = self.mode.code(byte) {
+                self.code(code);
+                i += 1;
+                continue;
+            }
+
+            let Some(mode) = Mode::TEXT.into_iter().filter(|mode| mode.code(byte).is_some()).max_by_key(|&mode| self.run(mode, i)) else {
+                let end = (i + 1..data.len()).find(|&end| self.starts_text(end)).unwrap_or(data.len());
+                self.binary_shift(&data[i..end]);
+                i = end;
+                continue;
+            };
+
+            let single = self.run(mode, i) == 1;
+            match mode {
+                Mode::Punct if single && self.mode != Mode::Punct => {
+                    self.code(0);
+                    self.bits.push(Mode::Punct.code(byte).expect("byte is punctuation").into(), 5);
+                },
+                Mode::Upper if single && matches!(self.mode, Mode::Lower | Mode::Digit) => {
+                    self.code(if self.mode == Mode::Lower { 28 } else { 15 });
+                    self.bits.push(Mode::Upper.code(byte).expect("byte is uppercase").into(), 5);
+                },
+                _ => {
+                    self.latch(mode);
+                    self.code(mode.code(byte).expect("mode was picked for byte"));
+                },
+            }
+            i += 1;
+        }
+        self.bits
+    }
+}
+
+fn word_size(layers: usize) -> usize {
+    match layers {
+        1..=2 => 6,
+        3..=8 => 8,
+        9..=22 => 10,
+        _ => 12,
+    }
+}
+
+fn field(word_size: usize) -> GaloisField {
+    match word_size {
+        4 => GaloisField::new(4, 0x13),
+        6 => GaloisField::new(6, 0x43),
+        8 => GaloisField::new(8, 0x12d),
+        10 => GaloisField::new(10, 0x409),
+        _ => GaloisField::new(12, 0x1069),
+    }
+}
+
+fn total_bits(compact: bool, layers: usize) -> usize {
+    (if compact { 88 } else { 112 } + 16 * layers) * layers
+}
+
+// words whose first bits are all equal get a complementary bit added, so no
+// word is all zeros or all ones; the last word is padded with ones
+fn stuff_bits(bits: &[bool], word_size: usize) -> Vec<usize> {
+    let mut words = Vec::new();
+    let mut i = 0;
+    // an empty message still takes a padding word, as the mode message
+    // counts at least one
+    while i < bits.len() || words.is_empty() {
+        let mut word = 0;
+        for j in 0..word_size {
+            if bits.get(i + j).copied().unwrap_or(true) {
+                word |= 1 << (word_size - 1 - j);
+            }
+        }
+        // a stuffed bit takes the place of the word's last data bit
+        let mask = (1 << word_size) - 2;
+        if word & mask == mask {
+            words.push(word & mask);
+            i += word_size - 1;
+        } else if word & mask == 0 {
+            words.push(word | 1);
+            i += word_size - 1;
+        } else {
+            words.push(word);
+            i += word_size;
+        }
+    }
+    words
+}
+
+// `words` followed by check words up to `total_bits`, as bits
+fn add_check_words(words: &[usize], word_size: usize, total_bits: usize) -> Vec<bool> {
+    let data: Vec<u16> = words.iter().map(|&word| word as u16).collect();
+    let ec = field(word_size).ec_codewords(&data, total_bits / word_size - words.len(), 1);
+    let mut bits = BitBuffer::default();
+    bits.push(0, total_bits % word_size);
+    for word in data.into_iter().chain(ec) {
+        bits.push(word.into(), word_size);
+    }
+    bits.0
+}
+
+/// An encoded Aztec symbol, row by row.
+pub(super) struct Aztec {
+    pub compact: bool,
+    pub layers: usize,
+    pub width: usize,
+    pub modules: Vec<Module>,
+}
+
+pub(super) fn encode(data: &[u8], eci: Option<Charset>, gs1: bool, format: AztecFormat, ec_percent: u8) -> Result<Aztec, GenerateError> {
+    if !(MIN_EC_PERCENT..=MAX_EC_PERCENT).contains(&ec_percent) {
+        return Err(GenerateError::InvalidEcPercent(ec_percent));
+    }
+
+    let mut encoder = Encoder { data, gs1, mode: Mode::Upper, bits: BitBuffer::default() };
+    if gs1 {
+        encoder.flag("");
+    }
+    if let Some(charset) = eci {
+        encoder.flag(&charset.eci_designator().to_string());
+    }
+    let bits = encoder.encode().0;
+
+    let ec_bits = bits.len() * ec_percent as usize / 100 + 11;
+    let sizes: Vec<(bool, usize)> = match format {
+        AztecFormat::Auto => (1..=4).map(|layers| (true, layers)).chain((4..=MAX_LAYERS).map(|layers| (false, layers))).collect(),
+        AztecFormat::Compact => (1..=4).map(|layers| (true, layers)).collect(),
+        AztecFormat::FullRange => (1..=MAX_LAYERS).map(|layers| (false, layers)).collect(),
+    };
+    let fits = |&(compact, layers): &(bool, usize)| {
+        let total_bits = total_bits(compact, layers);
+        let word_size = word_size(layers);
+        let words = stuff_bits(&bits, word_size);
+        // the compact mode message counts at most 64 data words
+        bits.len() + ec_bits <= total_bits
+            && !(compact && words.len() > 64)
+            && words.len() * word_size + ec_bits <= total_bits - total_bits % word_size
+    };
+    let Some(&(compact, layers)) = sizes.iter().find(|size| fits(size)) else {
+        let &(compact, layers) = sizes.last().expect("sizes is never empty");
+        return Err(GenerateError::SymbolTooLong {
+            symbology: Symbology::Aztec,
+            length: (bits.len() + ec_bits).div_ceil(8),
+            limit: total_bits(compact, layers) / 8,
+        });
+    };
+
+    let word_size = word_size(layers);
+    let words = stuff_bits(&bits, word_size);
+    let message = add_check_words(&words, word_size, total_bits(compact, layers));
+    let mode_message = mode_message(compact, layers, words.len());
+    let (width, modules) = draw(compact, layers, &message, &mode_message);
+    Ok(Aztec { compact, layers, width, modules })
+}
+
+// the layer count and data word count, protected by check words over GF(16)
+fn mode_message(compact: bool, layers: usize, data_words: usize) -> Vec<bool> {
+    let mut bits = BitBuffer::default();
+    if compact {
+        bits.push(layers - 1, 2);
+        bits.push(data_words - 1, 6);
+    } else {
+        bits.push(layers - 1, 5);
+        bits.push(data_words - 1, 11);
+    }
+    let words: Vec<usize> = bits.0.chunks(4)
+        .map(|chunk| chunk.iter().fold(0, |word, &bit| word << 1 | usize::from(bit)))
+        .collect();
+    add_check_words(&words, 4, if compact { 28 } else { 40 })
+}
+
+fn draw(compact: bool, layers: usize, message: &[bool], mode_message: &[bool]) -> (usize, Vec<Module>) {
+    // full-range symbols skip a reference grid line every 16 modules, so
+    // positions in the data layers map onto the matrix with gaps
+    let base_size = if compact { 11 } else { 14 } + layers * 4;
+    let (size, alignment) = if compact {
+        (base_size, (0..base_size).collect::<Vec<_>>())
+    } else {
+        let size = base_size + 1 + 2 * ((base_size / 2 - 1) / 15);
+        let (original_center, center) = (base_size / 2, size / 2);
+        let mut alignment = vec![0; base_size];
+        for i in 0..original_center {
+            let offset = i + i / 15;
+            alignment[original_center - i - 1] = center - offset - 1;
+            alignment[original_center + i] = center + offset + 1;
+        }
+        (size, alignment)
+    };
+
+    let mut modules = vec![Module::Light; size * size];
+    let mut set = |x: usize, y: usize| modules[y * size + x] = Module::Dark;
+
+    // each layer is two modules thick and filled in a spiral, one side at a time
+    let mut offset = 0;
+    for layer in 0..layers {
+        let row_size = (layers - layer) * 4 + if compact { 9 } else { 12 };
+        let (near, far) = (layer * 2, base_size - 1 - layer * 2);
+        for j in 0..row_size {
+            for k in 0..2 {
+                let bit = |side: usize| message[offset + side * row_size * 2 + j * 2 + k];
+                if bit(0) {
+                    set(alignment[near + k], alignment[near + j]);
+                }
+                if bit(1) {
+                    set(alignment[near + j], alignment[far - k]);
+                }
+                if bit(2) {
+                    set(alignment[far - k], alignment[far - j]);
+                }
+                if bit(3) {
+                    set(alignment[far - j], alignment[near + k]);
+                }
+            }
+        }
+        offset += row_size * 8;
+    }
+
+    // the mode message runs around the bull's-eye, skipping the grid line
+    let center = size / 2;
+    if compact {
+        for i in 0..7 {
+            let offset = center - 3 + i;
+            if mode_message[i] {
+                set(offset, center - 5);
+            }
+            if mode_message[i + 7] {
+                set(center + 5, offset);
+            }
+            if mode_message[20 - i] {
+                set(offset, center + 5);
+            }
+            if mode_message[27 - i] {
+                set(center - 5, offset);
+            }
+        }
+    } else {
+        for i in 0..10 {
+            let offset = center - 5 + i + i / 5;
+            if mode_message[i] {
+                set(offset, center - 7);
+            }
+            if mode_message[i + 10] {
+                set(center + 7, offset);
+            }
+            if mode_message[29 - i] {
+                set(offset, center + 7);
+            }
+            if mode_message[39 - i] {
+                set(center - 7, offset);
+            }
+        }
+    }
+
+    // bull's-eye rings and the orientation marks at its corners
+    let radius = if compact { 5 } else { 7 };
+    for ring in (0..radius).step_by(2) {
+        for i in center - ring..=center + ring {
+            set(i, center - ring);
+            set(i, center + ring);
+            set(center - ring, i);
+            set(center + ring, i);
+        }
+    }
+    set(center - radius, center - radius);
+    set(center - radius + 1, center - radius);
+    set(center - radius, center - radius + 1);
+    set(center + radius, center - radius);
+    set(center + radius, center - radius + 1);
+    set(center + radius, center + radius - 1);
+
+    if !compact {
+        for distance in (0..base_size / 2 - 1).step_by(15).map(|i| i / 15 * 16) {
+            for k in (center & 1..size).step_by(2) {
+                set(center - distance, k);
+                set(center + distance, k);
+                set(k, center - distance);
+                set(k, center + distance);
+            }
+        }
+    }
+
+    (size, modules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "0010 1" as bits
+    fn bits(text: &str) -> Vec<bool> {
+        text.bytes().filter(|&c| c != b' ').map(|c| c == b'1').collect()
+    }
+
+    fn encoded(data: &[u8]) -> Vec<bool> {
+        Encoder { data, gs1: false, mode: Mode::Upper, bits: BitBuffer::default() }.encode().0
+    }
+
+    fn words(bits: &[bool], word_size: usize) -> Vec<u16> {
+        bits.chunks(word_size).map(|word| word.iter().fold(0, |value, &bit| value << 1 | u16::from(bit))).collect()
+    }
+
+    #[test]
+    fn latches_and_shifts_between_modes() {
+        // A, latch to lower, b, latch to digit, 1
+        assert_eq!(encoded(b"Ab1"), bits("00010 11100 00011 11110 0011"));
+        // latch to lower, a, shift to punctuation, !
+        assert_eq!(encoded(b"a!"), bits("11100 00010 00000 00110"));
+        // binary shift of one byte
+        assert_eq!(encoded(&[0xff]), bits("11111 00001 11111111"));
+    }
+
+    #[test]
+    fn stuffs_words_of_equal_bits() {
+        assert_eq!(stuff_bits(&bits("111111 000000"), 6), [0b111110, 0b100000, 0b011111]);
+        assert_eq!(stuff_bits(&bits("000000 000000"), 6), [0b000001, 0b000001, 0b001111]);
+        assert_eq!(stuff_bits(&bits("101010"), 6), [0b101010]);
+        assert_eq!(stuff_bits(&[], 6), [0b111110]);
+    }
+
+    #[test]
+    fn sizes_match_the_standard() {
+        let width = |compact, layers| draw(compact, layers, &vec![false; total_bits(compact, layers)], &[false; 40]).0;
+        assert_eq!((1..=4).map(|layers| width(true, layers)).collect::<Vec<_>>(), [15, 19, 23, 27]);
+        // full-range symbols grow a reference grid line on each side every 16 modules
+        assert_eq!([1, 4, 5, 12, 13, 32].map(|layers| width(false, layers)), [19, 31, 37, 67, 71, 151]);
+    }
+
+    #[test]
+    fn check_words_verify() {
+        for (compact, layers) in [(true, 1), (true, 4), (false, 2), (false, 6), (false, 12), (false, 23)] {
+            let word_size = word_size(layers);
+            let data: Vec<usize> = (0..10).map(|i| (i * 7 + 3) % ((1 << word_size) - 1) + 1).collect();
+            let total = total_bits(compact, layers);
+            let message = add_check_words(&data, word_size, total);
+            let codewords = words(&message[total % word_size..], word_size);
+            let ec_len = codewords.len() - data.len();
+            // a multiple of the generator leaves no remainder
+            assert_eq!(field(word_size).ec_codewords(&codewords, ec_len, 1), vec![0; ec_len], "{layers} layers");
+        }
+        for (compact, ec_len) in [(true, 5), (false, 6)] {
+            let codewords = words(&mode_message(compact, 2, 9), 4);
+            assert_eq!(field(4).ec_codewords(&codewords, ec_len, 1), vec![0; ec_len]);
+        }
+    }
+
+    #[test]
+    fn bulls_eye_is_at_the_center() {
+        for format in [AztecFormat::Compact, AztecFormat::FullRange] {
+            let symbol = encode(b"Aztec Code", None, false, format, DEFAULT_EC_PERCENT).unwrap();
+            let rings = if symbol.compact { 4 } else { 6 };
+            let center = symbol.width / 2;
+            for y in center - rings..=center + rings {
+                for x in center - rings..=center + rings {
+                    let ring = x.abs_diff(center).max(y.abs_diff(center));
+                    assert_eq!(symbol.modules[y * symbol.width + x] == Module::Dark, ring % 2 == 0, "{format} ({x}, {y})");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty_data_fits_the_smallest_symbol() {
+        let symbol = encode(b"", None, false, AztecFormat::Auto, DEFAULT_EC_PERCENT).unwrap();
+        assert_eq!((symbol.compact, symbol.layers), (true, 1));
+    }
+
+    #[test]
+    fn rejects_error_correction_out_of_range() {
+        for ec_percent in [MIN_EC_PERCENT - 1, MAX_EC_PERCENT + 1] {
+            assert!(matches!(encode(b"A", None, false, AztecFormat::Auto, ec_percent), Err(GenerateError::InvalidEcPercent(percent)) if percent == ec_percent));
+        }
+    }
+}
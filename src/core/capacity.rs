@@ -0,0 +1,188 @@
+//! How much of a symbol a payload fills, computed without drawing anything so
+//! it is cheap enough to run on every keystroke.
+
+use qrcode::bits::Bits;
+use qrcode::types::{EcLevel, Mode, Version};
+
+use super::segment::{self, Segment};
+use super::{encode, Charset, GeneratedQr, GenerateError, QrOptions, QrRequest};
+
+// ECI mode indicator plus an 8 bit designator, enough for every `Charset`
+pub(super) const ECI_HEADER_BITS: usize = 12;
+// FNC1 in first position is a mode indicator alone
+pub(super) const FNC1_HEADER_BITS: usize = 4;
+
+/// How a payload fits the allowed versions at one error correction level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Capacity {
+    pub ec_level: EcLevel,
+    /// Size of the payload after any ECI transcoding.
+    pub payload_bytes: usize,
+    /// The cheapest single mode that can hold the whole payload.
+    pub mode: Mode,
+    /// The version the payload would be encoded in, or `None` if it does not fit.
+    pub version: Option<Version>,
+    /// Data bits the payload takes up in the largest allowed version.
+    pub used_bits: usize,
+    /// Data bits the largest allowed version holds.
+    pub max_bits: usize,
+}
+
+impl Capacity {
+    pub fn fits(&self) -> bool {
+        self.version.is_some()
+    }
+
+    pub fn remaining_bits(&self) -> usize {
+        self.max_bits.saturating_sub(self.used_bits)
+    }
+
+    /// Roughly how many more characters of `mode` would still fit.
+    pub fn remaining_characters(&self) -> usize {
+        let bits = self.remaining_bits();
+        match self.mode {
+            Mode::Numeric => bits * 3 / 10,
+            Mode::Alphanumeric => bits * 2 / 11,
+            Mode::Byte => bits / 8,
+            Mode::Kanji => bits / 13,
+        }
+    }
+}
+
+/// How a Kanji mode symbol compares to encoding the same text as UTF-8 in
+/// byte mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KanjiSavings {
+    /// The first character Shift-JIS lacks, which left the text in UTF-8.
+    pub unsupported: Option<char>,
+    pub bits: usize,
+    pub utf8_bits: usize,
+}
+
+impl KanjiSavings {
+    /// Bits saved over UTF-8 byte mode; negative if Kanji mode came out larger.
+    pub fn saved_bits(&self) -> isize {
+        self.utf8_bits as isize - self.bits as isize
+    }
+}
+
+/// Most characters of `mode` that fit in `version` at `ec_level` as a single segment.
+pub fn character_limit(version: Version, ec_level: EcLevel, mode: Mode, eci: bool) -> usize {
+    let Ok(max_bits) = Bits::new(version).max_len(ec_level) else {
+        return 0;
+    };
+    let eci_bits = if eci { ECI_HEADER_BITS } else { 0 };
+    let bits = max_bits.saturating_sub(version.mode_bits_count() + mode.length_bits_count(version) + eci_bits);
+    let characters = match mode {
+        // leftover digits are packed into 4 or 7 bits
+        Mode::Numeric => bits / 10 * 3 + match bits % 10 { 7.. => 2, 4.. => 1, _ => 0 },
+        Mode::Alphanumeric => bits / 11 * 2 + usize::from(bits % 11 >= 6),
+        Mode::Byte => bits / 8,
+        Mode::Kanji => bits / 13,
+    };
+    characters.min((1 << mode.length_bits_count(version)) - 1)
+}
+
+/// The mode with the fewest bits per character that accepts all of `data`.
+/// Kanji mode is only considered for Shift-JIS data.
+pub fn cheapest_mode(data: &[u8], allow_kanji: bool, gs1: bool) -> Mode {
+    [Mode::Numeric, Mode::Alphanumeric, Mode::Kanji]
+        .into_iter()
+        .filter(|&mode| mode != Mode::Kanji || allow_kanji)
+        .find(|&mode| segment::mode_accepts(mode, data, gs1))
+        .unwrap_or(Mode::Byte)
+}
+
+impl QrRequest {
+    /// Measures the payload against the allowed versions. With an automatic
+    /// EC level, this reports the level `generate` would pick.
+    pub fn capacity(&self) -> Result<Capacity, GenerateError> {
+        if !self.options.auto_ec_level {
+            return self.capacity_at(self.options.ec_level);
+        }
+
+        // if none fit, this ends up reporting against the lowest level
+        let mut capacity = self.capacity_at(EcLevel::H);
+        for ec_level in [EcLevel::Q, EcLevel::M, EcLevel::L] {
+            match capacity {
+                Ok(level) if level.fits() => break,
+                Ok(_) | Err(GenerateError::UnsupportedEcLevel { .. }) => capacity = self.capacity_at(ec_level),
+                Err(_) => break,
+            }
+        }
+        capacity
+    }
+
+    /// What Kanji mode saved in `code`, if this request uses it for text.
+    pub fn kanji_savings(&self, code: &GeneratedQr) -> Option<KanjiSavings> {
+        if !self.options.kanji_mode || self.options.eci.is_some() {
+            return None;
+        }
+
+        let version = code.version()?;
+        let text = std::str::from_utf8(&self.data).ok()?;
+        let utf8 = Segment { mode: Mode::Byte, begin: 0, end: text.len() };
+        Some(KanjiSavings {
+            unsupported: Charset::ShiftJis.encode(text).err(),
+            bits: code.data_bits(),
+            utf8_bits: utf8.encoded_len(version),
+        })
+    }
+
+    /// The error for a payload that does not fit `versions`, measured in its
+    /// cheapest mode.
+    pub(super) fn too_long(&self, payload: &[u8], versions: &[Version]) -> GenerateError {
+        let QrOptions { symbology, ec_level, .. } = self.options;
+        let largest = *versions.last().expect("versions is never empty");
+        let mode = cheapest_mode(payload, self.options.allows_kanji(), self.gs1);
+        GenerateError::DataTooLong {
+            symbology,
+            max_version: self.options.max_version,
+            ec_level,
+            mode,
+            length: if mode == Mode::Kanji { payload.len() / 2 } else { payload.len() },
+            limit: character_limit(largest, ec_level, mode, self.options.eci.is_some()),
+            fitting_ec_level: None,
+        }
+    }
+
+    /// Fills in which lower error correction level would fit, if any.
+    pub(super) fn explain(&self, e: GenerateError) -> GenerateError {
+        match e {
+            GenerateError::DataTooLong { symbology, max_version, ec_level, mode, length, limit, .. } => {
+                let fitting_ec_level = [EcLevel::Q, EcLevel::M, EcLevel::L].into_iter()
+                    .filter(|&level| level < ec_level)
+                    .find(|&level| self.capacity_at(level).is_ok_and(|capacity| capacity.fits()));
+                GenerateError::DataTooLong { symbology, max_version, ec_level, mode, length, limit, fitting_ec_level }
+            },
+            e => e,
+        }
+    }
+
+    fn capacity_at(&self, ec_level: EcLevel) -> Result<Capacity, GenerateError> {
+        let request = QrRequest { options: QrOptions { ec_level, ..self.options }, ..self.clone() };
+        let versions = request.versions()?;
+        let payload = request.payload()?;
+        if let Some(segments) = &self.segments {
+            segment::validate_segments(&payload, segments, self.gs1)?;
+        }
+
+        let largest = *versions.last().expect("versions is never empty");
+        let segments: Vec<Segment> = match &self.segments {
+            Some(segments) => segments.clone(),
+            None => segment::optimal_segments(&payload, largest, request.options.allows_kanji(), self.gs1),
+        };
+        let eci_bits = if self.options.eci.is_some() { ECI_HEADER_BITS } else { 0 };
+        let fnc1_bits = if self.gs1 { FNC1_HEADER_BITS } else { 0 };
+        let used_bits = eci_bits + fnc1_bits + segments.iter()
+            .map(|segment| segment::encoded_len(&payload, segment, largest, self.gs1))
+            .sum::<usize>();
+        let max_bits = Bits::new(largest).max_len(ec_level)?;
+
+        let version = versions.into_iter()
+            .find(|&version| encode(&payload, version, &request.options, self.gs1, self.segments.as_deref(), false).is_ok());
+
+        let mode = cheapest_mode(&payload, request.options.allows_kanji(), self.gs1);
+        Ok(Capacity { ec_level, payload_bytes: payload.len(), mode, version, used_bits, max_bits })
+    }
+}
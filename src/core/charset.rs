@@ -0,0 +1,156 @@
+//! Character sets a payload can be declared in with an ECI designator. Text is
+//! transcoded from UTF-8 before encoding so the bytes match the declaration.
+
+use std::fmt;
+
+use encoding_rs::Encoding;
+
+/// A character set together with its ECI assignment number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Charset {
+    Utf8,
+    Iso8859_1,
+    Iso8859_2,
+    Iso8859_5,
+    Iso8859_7,
+    Iso8859_15,
+    ShiftJis,
+    Windows1251,
+    Windows1252,
+    Big5,
+    Gb18030,
+    EucKr,
+}
+
+impl Charset {
+    pub const ALL: [Charset; 12] = [
+        Charset::Utf8,
+        Charset::Iso8859_1,
+        Charset::Iso8859_2,
+        Charset::Iso8859_5,
+        Charset::Iso8859_7,
+        Charset::Iso8859_15,
+        Charset::ShiftJis,
+        Charset::Windows1251,
+        Charset::Windows1252,
+        Charset::Big5,
+        Charset::Gb18030,
+        Charset::EucKr,
+    ];
+
+    /// The assignment number written in the ECI header.
+    pub fn eci_designator(self) -> u32 {
+        match self {
+            Charset::Iso8859_1 => 3,
+            Charset::Iso8859_2 => 4,
+            Charset::Iso8859_5 => 7,
+            Charset::Iso8859_7 => 9,
+            Charset::Iso8859_15 => 17,
+            Charset::ShiftJis => 20,
+            Charset::Windows1251 => 22,
+            Charset::Windows1252 => 23,
+            Charset::Utf8 => 26,
+            Charset::Big5 => 28,
+            Charset::Gb18030 => 29,
+            Charset::EucKr => 30,
+        }
+    }
+
+    /// The character set an ECI header with this assignment number declares.
+    pub fn from_eci_designator(designator: u32) -> Option<Charset> {
+        Charset::ALL.into_iter().find(|charset| charset.eci_designator() == designator)
+    }
+
+    /// Short name accepted on the command line, e.g. `iso-8859-2`.
+    pub fn label(self) -> &'static str {
+        match self {
+            Charset::Utf8 => "utf-8",
+            Charset::Iso8859_1 => "iso-8859-1",
+            Charset::Iso8859_2 => "iso-8859-2",
+            Charset::Iso8859_5 => "iso-8859-5",
+            Charset::Iso8859_7 => "iso-8859-7",
+            Charset::Iso8859_15 => "iso-8859-15",
+            Charset::ShiftJis => "shift-jis",
+            Charset::Windows1251 => "windows-1251",
+            Charset::Windows1252 => "windows-1252",
+            Charset::Big5 => "big5",
+            Charset::Gb18030 => "gb18030",
+            Charset::EucKr => "euc-kr",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Charset> {
+        // "ISO_8859-2", "iso88592" and "iso-8859-2" all name the same charset
+        let normalize = |label: &str| label.to_ascii_lowercase().replace(['-', '_'], "");
+        let label = normalize(label);
+        Charset::ALL.into_iter().find(|charset| normalize(charset.label()) == label)
+    }
+
+    // ISO-8859-1 is missing: encoding_rs treats that label as windows-1252
+    fn encoding(self) -> Option<&'static Encoding> {
+        match self {
+            Charset::Utf8 | Charset::Iso8859_1 => None,
+            Charset::Iso8859_2 => Some(encoding_rs::ISO_8859_2),
+            Charset::Iso8859_5 => Some(encoding_rs::ISO_8859_5),
+            Charset::Iso8859_7 => Some(encoding_rs::ISO_8859_7),
+            Charset::Iso8859_15 => Some(encoding_rs::ISO_8859_15),
+            Charset::ShiftJis => Some(encoding_rs::SHIFT_JIS),
+            Charset::Windows1251 => Some(encoding_rs::WINDOWS_1251),
+            Charset::Windows1252 => Some(encoding_rs::WINDOWS_1252),
+            Charset::Big5 => Some(encoding_rs::BIG5),
+            Charset::Gb18030 => Some(encoding_rs::GB18030),
+            Charset::EucKr => Some(encoding_rs::EUC_KR),
+        }
+    }
+
+    /// Transcodes `text` into this character set, or returns the first
+    /// character it has no representation for.
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, char> {
+        match self.encoding() {
+            None if self == Charset::Utf8 => Ok(text.as_bytes().to_vec()),
+            None => text.chars().map(|c| u8::try_from(c).map_err(|_| c)).collect(),
+            Some(encoding) => {
+                // encode() silently substitutes HTML entities, so go character by character
+                let mut bytes = Vec::with_capacity(text.len());
+                let mut buffer = [0; 4];
+                for c in text.chars() {
+                    let (encoded, _, unmappable) = encoding.encode(c.encode_utf8(&mut buffer));
+                    if unmappable {
+                        return Err(c);
+                    }
+                    bytes.extend_from_slice(&encoded);
+                }
+                Ok(bytes)
+            },
+        }
+    }
+
+    /// Reads `bytes` as text in this character set, the inverse of `encode`,
+    /// or `None` if they are not valid in it.
+    pub fn decode(self, bytes: &[u8]) -> Option<String> {
+        match self.encoding() {
+            None if self == Charset::Utf8 => String::from_utf8(bytes.to_vec()).ok(),
+            None => Some(bytes.iter().map(|&byte| char::from(byte)).collect()),
+            Some(encoding) => encoding.decode_without_bom_handling_and_without_replacement(bytes).map(|text| text.into_owned()),
+        }
+    }
+}
+
+impl fmt::Display for Charset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Charset::Utf8 => "UTF-8",
+            Charset::Iso8859_1 => "ISO-8859-1 (Latin-1)",
+            Charset::Iso8859_2 => "ISO-8859-2 (Central European)",
+            Charset::Iso8859_5 => "ISO-8859-5 (Cyrillic)",
+            Charset::Iso8859_7 => "ISO-8859-7 (Greek)",
+            Charset::Iso8859_15 => "ISO-8859-15 (Latin-9)",
+            Charset::ShiftJis => "Shift-JIS",
+            Charset::Windows1251 => "Windows-1251",
+            Charset::Windows1252 => "Windows-1252",
+            Charset::Big5 => "Big5",
+            Charset::Gb18030 => "GB18030",
+            Charset::EucKr => "EUC-KR",
+        })
+    }
+}
@@ -0,0 +1,203 @@
+//! Compressed file payloads, so text files fit in far fewer modules. The
+//! payload describes itself, letting [`unpack`] restore the original file
+//! from whatever a scanner read:
+//!
+//! | bytes | contents                                     |
+//! |-------|----------------------------------------------|
+//! | 3     | `QPZ`                                        |
+//! | 1     | method: 1 for raw deflate, 2 for Zstandard   |
+//! | 4     | original size, big-endian                    |
+//! | 1     | length of the file name                      |
+//! | n     | file name, UTF-8                             |
+//! | rest  | compressed data                              |
+
+use std::fmt;
+use std::io::Read;
+
+use ruzstd::decoding::StreamingDecoder;
+use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+
+const MAGIC: &[u8; 3] = b"QPZ";
+// magic, method, size and name length
+const HEADER_LEN: usize = 9;
+const DEFLATE_LEVEL: u8 = 10;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    Deflate,
+    Zstd,
+}
+
+impl Compression {
+    pub const ALL: [Compression; 2] = [Compression::Deflate, Compression::Zstd];
+
+    fn id(self) -> u8 {
+        match self {
+            Compression::Deflate => 1,
+            Compression::Zstd => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Compression> {
+        Compression::ALL.into_iter().find(|method| method.id() == id)
+    }
+
+    fn compress(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Compression::Deflate => miniz_oxide::deflate::compress_to_vec(data, DEFLATE_LEVEL),
+            Compression::Zstd => compress_to_vec(data, CompressionLevel::Fastest),
+        }
+    }
+
+    fn decompress(self, data: &[u8], size: usize) -> Option<Vec<u8>> {
+        match self {
+            Compression::Deflate => miniz_oxide::inflate::decompress_to_vec_with_limit(data, size).ok(),
+            Compression::Zstd => {
+                let mut decompressed = Vec::with_capacity(size);
+                StreamingDecoder::new(data).ok()?.take(size as u64 + 1).read_to_end(&mut decompressed).ok()?;
+                Some(decompressed)
+            },
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Compression::Deflate => "Deflate",
+            Compression::Zstd => "Zstandard",
+        })
+    }
+}
+
+/// A file restored from a compressed payload.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnpackedFile {
+    /// The name the file had when it was packed, possibly empty.
+    pub name: String,
+    pub method: Compression,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UnpackError {
+    /// The payload does not start with the header [`pack`] writes.
+    NotPacked,
+    UnknownMethod(u8),
+    /// The header or the compressed data is cut short or damaged.
+    Corrupt,
+    /// The data decompressed to a different size than the header records.
+    WrongSize { expected: usize, found: usize },
+}
+
+impl fmt::Display for UnpackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnpackError::NotPacked => write!(f, "This is not a compressed file payload."),
+            UnpackError::UnknownMethod(id) => write!(f, "The payload uses compression method {id}, which this version does not know."),
+            UnpackError::Corrupt => write!(f, "The compressed data is damaged or incomplete."),
+            UnpackError::WrongSize { expected, found } => {
+                write!(f, "The file should be {expected} bytes, but decompressed to {found}.")
+            },
+        }
+    }
+}
+
+impl std::error::Error for UnpackError {}
+
+/// Compresses `data` behind a header recording `method`, the original size
+/// and `name`, which is cut to 255 bytes.
+pub fn pack(data: &[u8], name: &str, method: Compression) -> Vec<u8> {
+    let mut end = name.len().min(u8::MAX as usize);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    let name = &name.as_bytes()[..end];
+
+    let mut payload = Vec::with_capacity(HEADER_LEN + name.len() + data.len());
+    payload.extend_from_slice(MAGIC);
+    payload.push(method.id());
+    payload.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    payload.push(name.len() as u8);
+    payload.extend_from_slice(name);
+    payload.extend(method.compress(data));
+    payload
+}
+
+/// Whether `payload` starts like something [`pack`] wrote.
+pub fn is_packed(payload: &[u8]) -> bool {
+    payload.starts_with(MAGIC)
+}
+
+/// Restores the file [`pack`] compressed into `payload`.
+pub fn unpack(payload: &[u8]) -> Result<UnpackedFile, UnpackError> {
+    if !is_packed(payload) {
+        return Err(UnpackError::NotPacked);
+    }
+    if payload.len() < HEADER_LEN {
+        return Err(UnpackError::Corrupt);
+    }
+    let method = Compression::from_id(payload[3]).ok_or(UnpackError::UnknownMethod(payload[3]))?;
+    let expected = u32::from_be_bytes(payload[4..8].try_into().expect("four bytes")) as usize;
+    let name_end = HEADER_LEN + payload[8] as usize;
+    let name = payload.get(HEADER_LEN..name_end).ok_or(UnpackError::Corrupt)?;
+    let name = String::from_utf8_lossy(name).into_owned();
+
+    let data = method.decompress(&payload[name_end..], expected).ok_or(UnpackError::Corrupt)?;
+    if data.len() != expected {
+        return Err(UnpackError::WrongSize { expected, found: data.len() });
+    }
+    Ok(UnpackedFile { name, method, data })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(lines: usize) -> Vec<u8> {
+        (0..lines).flat_map(|i| format!("line {i}: the quick brown fox jumps over the lazy dog\n").into_bytes()).collect()
+    }
+
+    #[test]
+    fn unpack_restores_what_pack_wrote() {
+        for method in Compression::ALL {
+            for data in [Vec::new(), b"x".to_vec(), text(200), (0..=255).collect()] {
+                let packed = pack(&data, "notes.txt", method);
+                assert!(is_packed(&packed));
+                assert_eq!(unpack(&packed), Ok(UnpackedFile { name: String::from("notes.txt"), method, data }));
+            }
+        }
+    }
+
+    #[test]
+    fn text_gets_smaller() {
+        let data = text(200);
+        for method in Compression::ALL {
+            assert!(pack(&data, "", method).len() < data.len() / 4, "{method} did not compress");
+        }
+    }
+
+    #[test]
+    fn long_names_are_cut_on_a_character_boundary() {
+        let name = "é".repeat(200);
+        let unpacked = unpack(&pack(b"data", &name, Compression::Deflate)).unwrap();
+        assert_eq!(unpacked.name, "é".repeat(127));
+    }
+
+    #[test]
+    fn rejects_foreign_and_damaged_payloads() {
+        let packed = pack(&text(20), "", Compression::Zstd);
+        assert_eq!(unpack(b"plain text"), Err(UnpackError::NotPacked));
+        assert_eq!(unpack(&packed[..6]), Err(UnpackError::Corrupt));
+        assert_eq!(unpack(&packed[..packed.len() / 2]).map(|file| file.data), Err(UnpackError::Corrupt));
+
+        let mut unknown = packed.clone();
+        unknown[3] = 9;
+        assert_eq!(unpack(&unknown), Err(UnpackError::UnknownMethod(9)));
+
+        let mut resized = packed;
+        resized[7] ^= 1;
+        assert!(matches!(unpack(&resized), Err(UnpackError::WrongSize { .. })));
+    }
+}
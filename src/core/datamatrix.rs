@@ -0,0 +1,396 @@
+//! Data Matrix ECC200 (ISO/IEC 16022): ASCII and Base 256 encodation,
+//! Reed-Solomon blocks and the module placement of every square and
+//! rectangular symbol size.
+
+use std::fmt;
+
+use qrcode::types::Color as Module;
+
+use super::reed_solomon::GaloisField;
+use super::{Charset, GenerateError, Symbology};
+
+const PAD: u8 = 129;
+const UPPER_SHIFT: u8 = 235;
+const FNC1: u8 = 232;
+const ECI: u8 = 241;
+const BASE256_LATCH: u8 = 231;
+
+/// Which family of symbol sizes to pick the smallest fitting one from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DataMatrixShape {
+    #[default]
+    Square,
+    /// The six rectangular sizes from 8×18 to 16×48, for narrow labels.
+    Rectangle,
+}
+
+impl DataMatrixShape {
+    pub const ALL: [DataMatrixShape; 2] = [DataMatrixShape::Square, DataMatrixShape::Rectangle];
+}
+
+impl fmt::Display for DataMatrixShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DataMatrixShape::Square => "Square",
+            DataMatrixShape::Rectangle => "Rectangle",
+        })
+    }
+}
+
+struct Size {
+    rows: usize,
+    columns: usize,
+    // modules of one data region, without its finder and timing pattern
+    region_rows: usize,
+    region_columns: usize,
+    data: usize,
+    ec_per_block: usize,
+    blocks: usize,
+}
+
+const fn size(rows: usize, columns: usize, region_rows: usize, region_columns: usize, data: usize, ec_per_block: usize, blocks: usize) -> Size {
+    Size { rows, columns, region_rows, region_columns, data, ec_per_block, blocks }
+}
+
+const SQUARE: [Size; 24] = [
+    size(10, 10, 8, 8, 3, 5, 1),
+    size(12, 12, 10, 10, 5, 7, 1),
+    size(14, 14, 12, 12, 8, 10, 1),
+    size(16, 16, 14, 14, 12, 12, 1),
+    size(18, 18, 16, 16, 18, 14, 1),
+    size(20, 20, 18, 18, 22, 18, 1),
+    size(22, 22, 20, 20, 30, 20, 1),
+    size(24, 24, 22, 22, 36, 24, 1),
+    size(26, 26, 24, 24, 44, 28, 1),
+    size(32, 32, 14, 14, 62, 36, 1),
+    size(36, 36, 16, 16, 86, 42, 1),
+    size(40, 40, 18, 18, 114, 48, 1),
+    size(44, 44, 20, 20, 144, 56, 1),
+    size(48, 48, 22, 22, 174, 68, 1),
+    size(52, 52, 24, 24, 204, 42, 2),
+    size(64, 64, 14, 14, 280, 56, 2),
+    size(72, 72, 16, 16, 368, 36, 4),
+    size(80, 80, 18, 18, 456, 48, 4),
+    size(88, 88, 20, 20, 576, 56, 4),
+    size(96, 96, 22, 22, 696, 68, 4),
+    size(104, 104, 24, 24, 816, 56, 6),
+    size(120, 120, 18, 18, 1050, 68, 6),
+    size(132, 132, 20, 20, 1304, 62, 8),
+    size(144, 144, 22, 22, 1558, 62, 10),
+];
+
+const RECTANGLE: [Size; 6] = [
+    size(8, 18, 6, 16, 5, 7, 1),
+    size(8, 32, 6, 14, 10, 11, 1),
+    size(12, 26, 10, 24, 16, 14, 1),
+    size(12, 36, 10, 16, 22, 18, 1),
+    size(16, 36, 14, 16, 32, 24, 1),
+    size(16, 48, 14, 22, 49, 28, 1),
+];
+
+/// An encoded Data Matrix, row by row.
+pub(super) struct DataMatrix {
+    pub rows: usize,
+    pub columns: usize,
+    pub modules: Vec<Module>,
+}
+
+pub(super) fn encode(data: &[u8], eci: Option<Charset>, gs1: bool, shape: DataMatrixShape) -> Result<DataMatrix, GenerateError> {
+    let mut codewords = Vec::new();
+    if gs1 {
+        codewords.push(FNC1);
+    }
+    if let Some(charset) = eci {
+        // every designator `Charset` has is below 127 and takes one codeword
+        codewords.extend([ECI, charset.eci_designator() as u8 + 1]);
+    }
+    let ascii = ascii_codewords(data, gs1);
+    if gs1 || ascii.len() <= base256_len(data.len()) {
+        codewords.extend(ascii);
+    } else {
+        push_base256(&mut codewords, data);
+    }
+
+    let sizes: &[Size] = match shape {
+        DataMatrixShape::Square => &SQUARE,
+        DataMatrixShape::Rectangle => &RECTANGLE,
+    };
+    let size = sizes.iter()
+        .find(|size| size.data >= codewords.len())
+        .ok_or_else(|| GenerateError::SymbolTooLong {
+            symbology: Symbology::DataMatrix,
+            length: codewords.len(),
+            limit: sizes.last().expect("sizes is never empty").data,
+        })?;
+
+    pad(&mut codewords, size.data);
+    let codewords = add_error_correction(&codewords, size);
+    Ok(DataMatrix { rows: size.rows, columns: size.columns, modules: draw(&codewords, size) })
+}
+
+// pairs of digits share a codeword, bytes above 127 need an upper shift
+fn ascii_codewords(data: &[u8], gs1: bool) -> Vec<u8> {
+    let mut codewords = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        match (data[i], data.get(i + 1)) {
+            (first @ b'0'..=b'9', Some(&second @ b'0'..=b'9')) => {
+                codewords.push(130 + (first - b'0') * 10 + (second - b'0'));
+                i += 1;
+            },
+            // GS1 separates variable length elements with FNC1
+            (0x1d, _) if gs1 => codewords.push(FNC1),
+            (byte @ 128.., _) => codewords.extend([UPPER_SHIFT, byte - 127]),
+            (byte, _) => codewords.push(byte + 1),
+        }
+        i += 1;
+    }
+    codewords
+}
+
+fn base256_len(len: usize) -> usize {
+    if len < 250 { len + 2 } else { len + 3 }
+}
+
+// Base 256 codewords are scrambled with a pseudo random sequence seeded by
+// their position so long runs of the same byte do not produce blank areas
+fn push_base256(codewords: &mut Vec<u8>, data: &[u8]) {
+    codewords.push(BASE256_LATCH);
+    let length = if data.len() < 250 {
+        vec![data.len() as u8]
+    } else {
+        vec![(data.len() / 250 + 249) as u8, (data.len() % 250) as u8]
+    };
+    for &byte in length.iter().chain(data) {
+        let position = codewords.len() + 1;
+        codewords.push(((byte as usize + 149 * position % 255 + 1) % 256) as u8);
+    }
+}
+
+fn pad(codewords: &mut Vec<u8>, len: usize) {
+    if codewords.len() < len {
+        codewords.push(PAD);
+    }
+    while codewords.len() < len {
+        let position = codewords.len() + 1;
+        let pad = PAD as usize + 149 * position % 253 + 1;
+        codewords.push(if pad > 254 { pad - 254 } else { pad } as u8);
+    }
+}
+
+// data and check codewords of the blocks are interleaved one by one
+fn add_error_correction(data: &[u8], size: &Size) -> Vec<u8> {
+    let field = GaloisField::new(8, 0x12d);
+    let mut codewords = data.to_vec();
+    codewords.resize(size.data + size.ec_per_block * size.blocks, 0);
+    for block in 0..size.blocks {
+        let block_data: Vec<u16> = data.iter().skip(block).step_by(size.blocks).map(|&c| c.into()).collect();
+        let ec = field.ec_codewords(&block_data, size.ec_per_block, 1);
+        for (i, codeword) in ec.into_iter().enumerate() {
+            codewords[size.data + i * size.blocks + block] = codeword as u8;
+        }
+    }
+    codewords
+}
+
+// the "utah" placement of ISO/IEC 16022 Annex F, over the data regions
+// joined into one matrix
+struct Placement<'a> {
+    rows: usize,
+    columns: usize,
+    codewords: &'a [u8],
+    modules: Vec<Option<bool>>,
+}
+
+impl Placement<'_> {
+    fn module(&mut self, mut row: isize, mut column: isize, codeword: usize, bit: u8) {
+        let (rows, columns) = (self.rows as isize, self.columns as isize);
+        if row < 0 {
+            row += rows;
+            column += 4 - (rows + 4) % 8;
+        }
+        if column < 0 {
+            column += columns;
+            row += 4 - (columns + 4) % 8;
+        }
+        let value = self.codewords[codeword] & (0x80 >> (bit - 1)) != 0;
+        self.modules[row as usize * self.columns + column as usize] = Some(value);
+    }
+
+    fn is_placed(&self, row: isize, column: isize) -> bool {
+        self.modules[row as usize * self.columns + column as usize].is_some()
+    }
+
+    fn utah(&mut self, row: isize, column: isize, codeword: usize) {
+        let positions = [(-2, -2), (-2, -1), (-1, -2), (-1, -1), (-1, 0), (0, -2), (0, -1), (0, 0)];
+        for (bit, (dr, dc)) in positions.into_iter().enumerate() {
+            self.module(row + dr, column + dc, codeword, bit as u8 + 1);
+        }
+    }
+
+    fn corner(&mut self, positions: [(isize, isize); 8], codeword: usize) {
+        for (bit, (row, column)) in positions.into_iter().enumerate() {
+            self.module(row, column, codeword, bit as u8 + 1);
+        }
+    }
+
+    fn place(mut self) -> Vec<bool> {
+        let (rows, columns) = (self.rows as isize, self.columns as isize);
+        let (mut codeword, mut row, mut column) = (0, 4, 0);
+        loop {
+            if row == rows && column == 0 {
+                self.corner([(rows - 1, 0), (rows - 1, 1), (rows - 1, 2), (0, columns - 2), (0, columns - 1), (1, columns - 1), (2, columns - 1), (3, columns - 1)], codeword);
+                codeword += 1;
+            }
+            if row == rows - 2 && column == 0 && columns % 4 != 0 {
+                self.corner([(rows - 3, 0), (rows - 2, 0), (rows - 1, 0), (0, columns - 4), (0, columns - 3), (0, columns - 2), (0, columns - 1), (1, columns - 1)], codeword);
+                codeword += 1;
+            }
+            if row == rows - 2 && column == 0 && columns % 8 == 4 {
+                self.corner([(rows - 3, 0), (rows - 2, 0), (rows - 1, 0), (0, columns - 2), (0, columns - 1), (1, columns - 1), (2, columns - 1), (3, columns - 1)], codeword);
+                codeword += 1;
+            }
+            if row == rows + 4 && column == 2 && columns % 8 == 0 {
+                self.corner([(rows - 1, 0), (rows - 1, columns - 1), (0, columns - 3), (0, columns - 2), (0, columns - 1), (1, columns - 3), (1, columns - 2), (1, columns - 1)], codeword);
+                codeword += 1;
+            }
+
+            // sweep up and to the right
+            loop {
+                if row < rows && column >= 0 && !self.is_placed(row, column) {
+                    self.utah(row, column, codeword);
+                    codeword += 1;
+                }
+                row -= 2;
+                column += 2;
+                if row < 0 || column >= columns {
+                    break;
+                }
+            }
+            row += 1;
+            column += 3;
+
+            // then down and to the left
+            loop {
+                if row >= 0 && column < columns && !self.is_placed(row, column) {
+                    self.utah(row, column, codeword);
+                    codeword += 1;
+                }
+                row += 2;
+                column -= 2;
+                if row >= rows || column < 0 {
+                    break;
+                }
+            }
+            row += 3;
+            column += 1;
+
+            if row >= rows && column >= columns {
+                break;
+            }
+        }
+
+        // sizes whose area is not a multiple of 8 leave a fixed corner pattern
+        let last = self.modules.len() - 1;
+        if self.modules[last].is_none() {
+            self.modules[last] = Some(true);
+            self.modules[last - self.columns - 1] = Some(true);
+        }
+        self.modules.into_iter().map(|module| module.unwrap_or(false)).collect()
+    }
+}
+
+fn draw(codewords: &[u8], size: &Size) -> Vec<Module> {
+    let vertical_regions = size.rows / (size.region_rows + 2);
+    let horizontal_regions = size.columns / (size.region_columns + 2);
+    let (rows, columns) = (size.region_rows * vertical_regions, size.region_columns * horizontal_regions);
+    let data = Placement { rows, columns, codewords, modules: vec![None; rows * columns] }.place();
+
+    let mut modules = vec![Module::Light; size.rows * size.columns];
+    for y in 0..size.rows {
+        for x in 0..size.columns {
+            let (region_y, region_x) = (y % (size.region_rows + 2), x % (size.region_columns + 2));
+            // each region has a solid L on its left and bottom, and
+            // alternating timing modules on its top and right
+            let dark = if region_x == 0 || region_y == size.region_rows + 1 {
+                true
+            } else if region_y == 0 {
+                x % 2 == 0
+            } else if region_x == size.region_columns + 1 {
+                y % 2 == 1
+            } else {
+                let row = y / (size.region_rows + 2) * size.region_rows + region_y - 1;
+                let column = x / (size.region_columns + 2) * size.region_columns + region_x - 1;
+                data[row * columns + column]
+            };
+            if dark {
+                modules[y * size.columns + x] = Module::Dark;
+            }
+        }
+    }
+    modules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dark_modules(modules: &[Module]) -> usize {
+        modules.iter().filter(|&&module| module == Module::Dark).count()
+    }
+
+    #[test]
+    fn encodes_the_standard_example() {
+        // ISO/IEC 16022 encodes "123456" in a 10×10 symbol as these codewords
+        let data = ascii_codewords(b"123456", false);
+        assert_eq!(data, [142, 164, 186]);
+        assert_eq!(add_error_correction(&data, &SQUARE[0]), [142, 164, 186, 114, 25, 5, 88, 102]);
+    }
+
+    #[test]
+    fn pads_with_scrambled_pad_codewords() {
+        let mut codewords = vec![b'A' + 1];
+        pad(&mut codewords, 5);
+        assert_eq!(codewords, [66, PAD, 70, 220, 115]);
+    }
+
+    #[test]
+    fn every_size_has_a_module_for_each_codeword_bit() {
+        for size in SQUARE.iter().chain(&RECTANGLE) {
+            let total = size.data + size.ec_per_block * size.blocks;
+            let (ones, zeros) = (draw(&vec![0xff; total], size), draw(&vec![0; total], size));
+            let regions = (size.rows / (size.region_rows + 2)) * (size.columns / (size.region_columns + 2));
+            let data_modules = regions * size.region_rows * size.region_columns;
+            // all but the few modules in the corner of some sizes hold codeword bits
+            assert_eq!(dark_modules(&ones) - dark_modules(&zeros), total * 8, "{}×{}", size.rows, size.columns);
+            assert!(data_modules - total * 8 < 8, "{}×{}", size.rows, size.columns);
+        }
+    }
+
+    #[test]
+    fn every_region_has_its_finder_and_timing_pattern() {
+        let symbol = encode(&[b'x'; 62], None, false, DataMatrixShape::Square).unwrap();
+        assert_eq!((symbol.rows, symbol.columns), (32, 32));
+        let dark = |x: usize, y: usize| symbol.modules[y * symbol.columns + x] == Module::Dark;
+        // four 16×16 regions, solid on the left and bottom, alternating on the top and right
+        for i in 0..symbol.columns {
+            for edge in [0, 16] {
+                assert!(dark(edge, i) && dark(i, edge + 15));
+                assert_eq!(dark(i, edge), i % 2 == 0);
+                assert_eq!(dark(edge + 15, i), i % 2 == 1);
+            }
+        }
+    }
+
+    #[test]
+    fn picks_the_smallest_symbol_of_the_shape() {
+        let sizes = |data: &[u8], shape| encode(data, None, false, shape).map(|symbol| (symbol.rows, symbol.columns));
+        assert_eq!(sizes(b"123456", DataMatrixShape::Square), Ok((10, 10)));
+        assert_eq!(sizes(b"123456", DataMatrixShape::Rectangle), Ok((8, 18)));
+        assert_eq!(sizes(&[b'x'; 49], DataMatrixShape::Rectangle), Ok((16, 48)));
+        assert_eq!(
+            sizes(&[b'x'; 50], DataMatrixShape::Rectangle),
+            Err(GenerateError::SymbolTooLong { symbology: Symbology::DataMatrix, length: 50, limit: 49 }),
+        );
+    }
+}
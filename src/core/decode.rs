@@ -0,0 +1,1227 @@
+//! Reading QR codes back out of images. The detector finds the three finder
+//! patterns, locates the bottom-right alignment pattern to correct for
+//! perspective, samples the module grid and then undoes what `qrcode` did:
+//! masking, interleaving, error correction and segment encoding. Micro QR
+//! codes, with their single finder pattern, are read when they sit square to
+//! the image.
+//!
+//! `decode` only reads dark modules on a light background, like most
+//! scanners; `decode_all`, for uploaded images, also tries the inverse.
+
+use std::fmt;
+
+use image::GrayImage;
+use qrcode::bits::Bits;
+use qrcode::canvas::is_functional;
+use qrcode::ec::construct_codewords;
+use qrcode::types::{EcLevel, Mode, Version};
+
+use super::charset::Charset;
+use super::reed_solomon::GaloisField;
+use super::structured_append::StructuredAppend;
+use super::symbology::Symbology;
+use super::{GeneratedQr, QrOptions, QrRequest, MAX_VERSION, MIN_VERSION};
+
+/// Smallest difference between the average dark and light pixel, out of 255,
+/// that still counts as a readable symbol. ISO/IEC 15415 grades a symbol
+/// contrast below 20% as a fail.
+pub const MIN_CONTRAST: u8 = 51;
+
+// most finder pattern candidates tried in combination
+const MAX_CANDIDATES: usize = 24;
+// most kept when reading every code in an image, three to a symbol
+const MAX_CANDIDATES_ALL: usize = 96;
+// the least distance, in modules, searched for the bottom-right alignment
+// pattern around where the finder patterns put it
+const MIN_ALIGNMENT_SEARCH_MODULES: f64 = 4.0;
+// most alignment pattern candidates tried for each version
+const MAX_ALIGNMENT_CANDIDATES: usize = 4;
+// the version and EC level each Micro QR symbol number in the format
+// information stands for
+const MICRO_SYMBOLS: [(i16, EcLevel); 8] = [
+    (1, EcLevel::L),
+    (2, EcLevel::L),
+    (2, EcLevel::M),
+    (3, EcLevel::L),
+    (3, EcLevel::M),
+    (4, EcLevel::L),
+    (4, EcLevel::M),
+    (4, EcLevel::Q),
+];
+// the QR mask each of the four Micro QR masks uses
+const MICRO_MASKS: [u8; 4] = [1, 4, 6, 7];
+
+/// Why no QR code could be read from an image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Dark and light modules are too close in brightness to tell apart.
+    LowContrast,
+    /// No three finder patterns form a QR code.
+    NotFound,
+    /// The format information, giving the error correction level and mask, is unreadable.
+    UnreadableFormat,
+    /// More codewords are damaged than the error correction can repair.
+    TooManyErrors,
+    /// The repaired data does not follow the QR code bit stream format.
+    InvalidData,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::LowContrast => write!(f, "The dark and light colors are too similar to tell apart."),
+            DecodeError::NotFound => write!(f, "No QR code was found."),
+            DecodeError::UnreadableFormat => write!(f, "The QR code's format information is unreadable."),
+            DecodeError::TooManyErrors => write!(f, "The QR code is too damaged for its error correction to repair."),
+            DecodeError::InvalidData => write!(f, "The QR code's data is corrupt."),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// A QR code read from an image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedQr {
+    pub version: Version,
+    pub ec_level: EcLevel,
+    pub mask: u8,
+    /// The data bytes, with Kanji as Shift-JIS and, in GS1 data, FNC1
+    /// separators as the GS character (0x1d).
+    pub payload: Vec<u8>,
+    /// The ECI designator of the payload's character set, if one was declared.
+    pub eci: Option<u32>,
+    pub structured_append: Option<StructuredAppend>,
+    /// Whether the data is flagged as GS1 with FNC1 in first position.
+    pub gs1: bool,
+    /// Whether any of the data was in Kanji mode, which holds Shift-JIS.
+    pub kanji: bool,
+    /// Codewords the error correction repaired.
+    pub corrected: usize,
+}
+
+impl DecodedQr {
+    /// The payload as text in its declared character set. Without a
+    /// declaration it is read as Shift-JIS if it has Kanji segments, and
+    /// otherwise (or if that fails) as UTF-8 and then ISO-8859-1. `None` for
+    /// binary data, which has control characters other than whitespace (and
+    /// GS separators).
+    pub fn text(&self) -> Option<String> {
+        let text = match self.eci {
+            Some(designator) => Charset::from_eci_designator(designator)?.decode(&self.payload)?,
+            None => self.kanji.then(|| Charset::ShiftJis.decode(&self.payload)).flatten()
+                .or_else(|| Charset::Utf8.decode(&self.payload))
+                .or_else(|| Charset::Iso8859_1.decode(&self.payload))?,
+        };
+        let printable = |c: char| !c.is_control() || matches!(c, '\t' | '\n' | '\r') || (self.gs1 && c == '\x1d');
+        text.chars().all(printable).then_some(text)
+    }
+
+    /// The payload as space-separated hex bytes, for data that is not text.
+    pub fn hex(&self) -> String {
+        self.payload.iter().map(|byte| format!("{byte:02x}")).collect::<Vec<_>>().join(" ")
+    }
+
+    /// A request that encodes the same data again with `options`. A payload
+    /// declared with an ECI or holding Kanji is passed on as text, to be
+    /// transcoded into the character set `options` asks for; anything else
+    /// keeps its bytes.
+    pub fn to_request(&self, options: QrOptions) -> QrRequest {
+        let data = match self.text() {
+            Some(text) if self.eci.is_some() || self.kanji => text.into_bytes(),
+            _ => self.payload.clone(),
+        };
+        QrRequest { gs1: self.gs1, ..QrRequest::new(data, options) }
+    }
+}
+
+/// Why a generated code did not survive being rendered and read back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifyError {
+    /// Only QR and Micro QR codes can be read back.
+    NotSupported(Symbology),
+    Unreadable(DecodeError),
+    /// The code scans, but not as the data it was generated from.
+    Mismatch,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::NotSupported(symbology) => write!(f, "{symbology} codes cannot be checked by reading them back."),
+            VerifyError::Unreadable(e) => write!(f, "The rendered code does not scan. {e}"),
+            VerifyError::Mismatch => write!(f, "The rendered code scans as different data."),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+impl VerifyError {
+    /// Whether the code was read back and failed, rather than not checked at all.
+    pub fn is_failure(&self) -> bool {
+        !matches!(self, VerifyError::NotSupported(_))
+    }
+}
+
+impl GeneratedQr {
+    /// Renders the symbol with `module_size` pixels per module, in its own
+    /// colors, and checks that reading the image gives back its payload.
+    pub fn verify(&self, module_size: u32) -> Result<(), VerifyError> {
+        if !self.options.symbology.is_qr() {
+            return Err(VerifyError::NotSupported(self.options.symbology));
+        }
+        self.verify_image(&image::DynamicImage::ImageRgb8(self.to_image(module_size)).to_luma8())
+    }
+
+    // whether `image`, a rendering of this QR code, reads back as its data
+    pub(super) fn verify_image(&self, image: &GrayImage) -> Result<(), VerifyError> {
+        let decoded = decode(image).map_err(VerifyError::Unreadable)?;
+        let matches = decoded.payload == self.payload
+            && decoded.structured_append == self.structured_append
+            && decoded.gs1 == self.gs1;
+        if matches { Ok(()) } else { Err(VerifyError::Mismatch) }
+    }
+}
+
+/// Reads the most prominent QR code in `image`.
+pub fn decode(image: &GrayImage) -> Result<DecodedQr, DecodeError> {
+    let bitmap = Bitmap::new(image)?;
+    let finders = bitmap.finder_patterns(MAX_CANDIDATES);
+    let mut error = DecodeError::NotFound;
+    for finders in finder_triples(&finders) {
+        match bitmap.decode_at(finders) {
+            Ok(decoded) => return Ok(decoded),
+            // keep the error from whichever candidate got the furthest
+            Err(e) => error = error.max_stage(e),
+        }
+    }
+    for finder in &finders {
+        match bitmap.decode_micro_at(finder) {
+            Ok(decoded) => return Ok(decoded),
+            Err(e) => error = error.max_stage(e),
+        }
+    }
+    Err(error)
+}
+
+/// Reads every QR code in `image`, in the order they were found. Light
+/// modules on a dark background are read too if no dark ones are.
+pub fn decode_all(image: &GrayImage) -> Result<Vec<DecodedQr>, DecodeError> {
+    let bitmap = Bitmap::new(image)?;
+    bitmap.decode_every().or_else(|error| bitmap.inverted().decode_every().map_err(|inverse| error.max_stage(inverse)))
+}
+
+impl DecodeError {
+    fn stage(self) -> u8 {
+        match self {
+            DecodeError::LowContrast => 0,
+            DecodeError::NotFound => 1,
+            DecodeError::UnreadableFormat => 2,
+            DecodeError::TooManyErrors => 3,
+            DecodeError::InvalidData => 4,
+        }
+    }
+
+    fn max_stage(self, other: DecodeError) -> DecodeError {
+        if other.stage() > self.stage() { other } else { self }
+    }
+}
+
+/// The image split into dark and light pixels.
+struct Bitmap {
+    width: usize,
+    height: usize,
+    dark: Vec<bool>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct Point {
+    pub(super) x: f64,
+    pub(super) y: f64,
+}
+
+impl Point {
+    fn distance(self, other: Point) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct FinderPattern {
+    center: Point,
+    module_size: f64,
+    // how many scan lines crossed it
+    hits: usize,
+}
+
+// whether five run lengths are close enough to 1:1:3:1:1, and if so the module size
+fn finder_module_size(runs: [f64; 5]) -> Option<f64> {
+    let total: f64 = runs.iter().sum();
+    if total < 7.0 {
+        return None;
+    }
+    let module = total / 7.0;
+    let tolerance = module / 2.0;
+    let fits = runs.iter().enumerate().all(|(i, &run)| {
+        let expected = if i == 2 { 3.0 } else { 1.0 };
+        (run - expected * module).abs() < expected * tolerance
+    });
+    fits.then_some(module)
+}
+
+impl Bitmap {
+    // splits at Otsu's threshold, the one that best separates the histogram
+    // into two classes
+    fn new(image: &GrayImage) -> Result<Bitmap, DecodeError> {
+        let mut histogram = [0usize; 256];
+        for pixel in image.pixels() {
+            histogram[pixel.0[0] as usize] += 1;
+        }
+        let total = image.width() as usize * image.height() as usize;
+        let sum: usize = histogram.iter().enumerate().map(|(value, &count)| value * count).sum();
+
+        let (mut best, mut threshold) = (0.0, 0);
+        let (mut dark_count, mut dark_sum) = (0, 0);
+        for (value, &count) in histogram.iter().enumerate() {
+            dark_count += count;
+            dark_sum += value * count;
+            if dark_count == 0 || dark_count == total {
+                continue;
+            }
+            let dark_mean = dark_sum as f64 / dark_count as f64;
+            let light_mean = (sum - dark_sum) as f64 / (total - dark_count) as f64;
+            let variance = dark_count as f64 * (total - dark_count) as f64 * (light_mean - dark_mean).powi(2);
+            if variance > best {
+                (best, threshold) = (variance, value);
+            }
+        }
+
+        let dark_count: usize = histogram[..=threshold].iter().sum();
+        if dark_count == 0 || dark_count == total {
+            return Err(DecodeError::LowContrast);
+        }
+        let dark_sum: usize = histogram[..=threshold].iter().enumerate().map(|(value, &count)| value * count).sum();
+        let contrast = (sum - dark_sum) as f64 / (total - dark_count) as f64 - dark_sum as f64 / dark_count as f64;
+        if contrast < MIN_CONTRAST as f64 {
+            return Err(DecodeError::LowContrast);
+        }
+
+        Ok(Bitmap {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            dark: image.pixels().map(|pixel| pixel.0[0] as usize <= threshold).collect(),
+        })
+    }
+
+    fn inverted(&self) -> Bitmap {
+        Bitmap { dark: self.dark.iter().map(|dark| !dark).collect(), ..*self }
+    }
+
+    fn is_dark(&self, x: isize, y: isize) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height && self.dark[y as usize * self.width + x as usize]
+    }
+
+    fn is_dark_at(&self, point: Point) -> bool {
+        self.is_dark(point.x.floor() as isize, point.y.floor() as isize)
+    }
+
+    // runs of alternating color through `from` in steps of `direction`: the
+    // run holding the point, then up to `side` runs each way, nearest first.
+    // The first element of each side is the middle run's extent in that direction.
+    fn runs_through(&self, from: Point, direction: Point, side: usize, limit: usize) -> (Vec<usize>, Vec<usize>) {
+        let walk = |sign: f64| {
+            let mut runs = vec![0];
+            let mut color = self.is_dark_at(from);
+            for step in 1..=limit {
+                let point = Point { x: from.x + sign * step as f64 * direction.x, y: from.y + sign * step as f64 * direction.y };
+                if point.x < 0.0 || point.y < 0.0 || point.x >= self.width as f64 || point.y >= self.height as f64 {
+                    break;
+                }
+                if self.is_dark_at(point) != color {
+                    if runs.len() > side {
+                        break;
+                    }
+                    runs.push(0);
+                    color = !color;
+                }
+                *runs.last_mut().expect("runs is never empty") += 1;
+            }
+            runs
+        };
+        (walk(-1.0), walk(1.0))
+    }
+
+    // the five runs of a finder pattern centered near `from` along
+    // `direction`, and the offset of the middle run's center from `from`
+    fn finder_runs(&self, from: Point, direction: Point, limit: usize) -> Option<([f64; 5], f64)> {
+        if !self.is_dark_at(from) {
+            return None;
+        }
+        let (before, after) = self.runs_through(from, direction, 2, limit);
+        if before.len() < 3 || after.len() < 3 {
+            return None;
+        }
+        let middle = before[0] + after[0] + 1;
+        let runs = [before[2], before[1], middle, after[1], after[2]].map(|run| run as f64);
+        Some((runs, (after[0] as f64 - before[0] as f64) / 2.0))
+    }
+
+    // checks for a finder pattern centered near (x, y) along one axis,
+    // returning the offset of its true center from (x, y) and the module size
+    fn cross_check(&self, x: isize, y: isize, (dx, dy): (isize, isize), limit: usize) -> Option<(f64, f64)> {
+        let center = Point { x: x as f64 + 0.5, y: y as f64 + 0.5 };
+        let (runs, offset) = self.finder_runs(center, Point { x: dx as f64, y: dy as f64 }, limit)?;
+        Some((offset, finder_module_size(runs)?))
+    }
+
+    // the module size of the finder pattern at `from`, measured along the
+    // line to another one so that a rotated symbol is not measured diagonally
+    fn module_size_towards(&self, from: &FinderPattern, to: &FinderPattern) -> f64 {
+        let distance = from.center.distance(to.center);
+        let direction = Point { x: (to.center.x - from.center.x) / distance, y: (to.center.y - from.center.y) / distance };
+        let limit = (from.module_size * 7.0) as usize;
+        match self.finder_runs(from.center, direction, limit) {
+            Some((runs, _)) => finder_module_size(runs).unwrap_or(from.module_size),
+            None => from.module_size,
+        }
+    }
+
+    fn finder_patterns(&self, limit: usize) -> Vec<FinderPattern> {
+        let mut found: Vec<FinderPattern> = Vec::new();
+        for y in 0..self.height {
+            let row = &self.dark[y * self.width..(y + 1) * self.width];
+            // (dark, start, length) of each run along the row
+            let mut runs: Vec<(bool, usize, usize)> = Vec::new();
+            for (x, &dark) in row.iter().enumerate() {
+                match runs.last_mut() {
+                    Some((color, _, length)) if *color == dark => *length += 1,
+                    _ => runs.push((dark, x, 1)),
+                }
+            }
+
+            for window in runs.windows(5).filter(|window| window[0].0) {
+                let Some(module) = finder_module_size(std::array::from_fn(|i| window[i].2 as f64)) else {
+                    continue;
+                };
+                let limit = (module * 7.0) as usize;
+                let (_, start, length) = window[2];
+                let x = (start + length / 2) as isize;
+                let Some((dy, vertical)) = self.cross_check(x, y as isize, (0, 1), limit) else {
+                    continue;
+                };
+                let cy = (y as f64 + dy).round() as isize;
+                let Some((dx, horizontal)) = self.cross_check(x, cy, (1, 0), limit) else {
+                    continue;
+                };
+                let center = Point { x: x as f64 + dx + 0.5, y: cy as f64 + 0.5 + (y as f64 + dy - cy as f64) };
+                let module_size = (module + vertical + horizontal) / 3.0;
+
+                match found.iter_mut().find(|pattern| {
+                    pattern.center.distance(center) < pattern.module_size * 2.0
+                        && (pattern.module_size - module_size).abs() < pattern.module_size.max(1.0)
+                }) {
+                    Some(pattern) => {
+                        let hits = pattern.hits as f64;
+                        pattern.center = Point {
+                            x: (pattern.center.x * hits + center.x) / (hits + 1.0),
+                            y: (pattern.center.y * hits + center.y) / (hits + 1.0),
+                        };
+                        pattern.module_size = (pattern.module_size * hits + module_size) / (hits + 1.0);
+                        pattern.hits += 1;
+                    },
+                    None => found.push(FinderPattern { center, module_size, hits: 1 }),
+                }
+            }
+        }
+        found.sort_by_key(|pattern| std::cmp::Reverse(pattern.hits));
+        found.truncate(limit);
+        found
+    }
+
+    // whether `center` is on the middle module of an alignment pattern: a
+    // dark module inside a light ring inside a dark one, looked at across,
+    // down and along both diagonals so that rotation does not matter
+    fn is_alignment_center(&self, center: Point, module_size: f64) -> bool {
+        if !self.is_dark_at(center) {
+            return false;
+        }
+        let diagonal = std::f64::consts::FRAC_1_SQRT_2;
+        [(1.0, 0.0), (0.0, 1.0), (diagonal, diagonal), (diagonal, -diagonal)].into_iter().all(|(x, y)| {
+            let expected = if x * y == 0.0 { module_size } else { module_size * std::f64::consts::SQRT_2 };
+            let (before, after) = self.runs_through(center, Point { x, y }, 2, (expected * 4.0) as usize);
+            let fits = |run: usize| (expected * 0.4..=expected * 1.8).contains(&(run as f64));
+            before.len() == 3 && after.len() == 3 && fits(before[0] + after[0] + 1) && fits(before[1]) && fits(after[1])
+        })
+    }
+
+    // the likeliest centers of the bottom-right alignment pattern, nearest to
+    // where it would be without perspective first. Perspective can move it
+    // further the larger the symbol, so the search widens with the dimension.
+    fn alignment_candidates(&self, [top_left, top_right, bottom_left]: [Point; 3], module_size: f64, dimension: usize) -> Vec<Point> {
+        let size = dimension as f64;
+        let scale = (size - 10.0) / (size - 7.0);
+        let predicted = Point {
+            x: top_left.x + scale * (top_right.x + bottom_left.x - 2.0 * top_left.x),
+            y: top_left.y + scale * (top_right.y + bottom_left.y - 2.0 * top_left.y),
+        };
+        let radius = (module_size * (size / 6.0).max(MIN_ALIGNMENT_SEARCH_MODULES)) as isize;
+        let step = ((module_size / 4.0) as usize).max(1);
+        let mut centers: Vec<Point> = (-radius..=radius).step_by(step)
+            .flat_map(|dy| (-radius..=radius).step_by(step).map(move |dx| Point { x: predicted.x + dx as f64, y: predicted.y + dy as f64 }))
+            .filter(|&center| self.is_alignment_center(center, module_size))
+            .collect();
+        centers.sort_by(|a, b| a.distance(predicted).total_cmp(&b.distance(predicted)));
+
+        // points that pass form a small patch around each candidate, whose
+        // middle is taken as its center
+        let mut patches: Vec<Vec<Point>> = Vec::new();
+        for center in centers {
+            match patches.iter_mut().find(|patch| patch[0].distance(center) <= module_size) {
+                Some(patch) => patch.push(center),
+                None => patches.push(vec![center]),
+            }
+        }
+        patches.iter()
+            .take(MAX_ALIGNMENT_CANDIDATES)
+            .map(|patch| {
+                let count = patch.len() as f64;
+                Point { x: patch.iter().map(|point| point.x).sum::<f64>() / count, y: patch.iter().map(|point| point.y).sum::<f64>() / count }
+            })
+            .collect()
+    }
+
+    // maps module coordinates to pixels, using the three finder patterns and
+    // the bottom-right alignment pattern, or without one, assuming the
+    // bottom-right corner completes a parallelogram
+    fn transform(&self, [top_left, top_right, bottom_left]: [Point; 3], alignment: Option<Point>, dimension: usize) -> Option<Perspective> {
+        let size = dimension as f64;
+        let far = size - 3.5;
+        let fourth = match alignment {
+            Some(center) => (Point { x: size - 6.5, y: size - 6.5 }, center),
+            None => (Point { x: far, y: far }, Point { x: top_right.x + bottom_left.x - top_left.x, y: top_right.y + bottom_left.y - top_left.y }),
+        };
+        Perspective::from_points([
+            (Point { x: 3.5, y: 3.5 }, top_left),
+            (Point { x: far, y: 3.5 }, top_right),
+            (Point { x: 3.5, y: far }, bottom_left),
+            fourth,
+        ])
+    }
+
+    fn sample(&self, transform: &Perspective, dimension: usize) -> Grid {
+        let dark = (0..dimension * dimension)
+            .map(|i| self.is_dark_at(transform.map(Point { x: (i % dimension) as f64 + 0.5, y: (i / dimension) as f64 + 0.5 })))
+            .collect();
+        Grid { size: dimension, dark }
+    }
+
+    // every symbol that decodes, each finder pattern belonging to at most one.
+    // QR codes are read first, then Micro QR codes around the patterns left.
+    // Three patterns around another are likely from neighbouring symbols, so
+    // they are tried last, and not at all around a symbol already read.
+    fn decode_every(&self) -> Result<Vec<DecodedQr>, DecodeError> {
+        let candidates = self.finder_patterns(MAX_CANDIDATES_ALL);
+        let (around, clear): (Vec<_>, Vec<_>) = finder_triples(&candidates).into_iter()
+            .partition(|finders| candidates.iter().any(|other| encloses(*finders, other.center)));
+        let mut used: Vec<Point> = Vec::new();
+        let mut decoded = Vec::new();
+        let mut error = DecodeError::NotFound;
+        for finders in clear.into_iter().chain(around) {
+            if finders.iter().any(|finder| used.contains(&finder.center)) || used.iter().any(|&center| encloses(finders, center)) {
+                continue;
+            }
+            match self.decode_at(finders) {
+                Ok(code) => {
+                    used.extend(finders.map(|finder| finder.center));
+                    decoded.push(code);
+                },
+                Err(e) => error = error.max_stage(e),
+            }
+        }
+        // finder patterns no QR code took may each be a Micro QR code
+        for finder in candidates.iter().filter(|finder| !used.contains(&finder.center)) {
+            match self.decode_micro_at(finder) {
+                Ok(code) => decoded.push(code),
+                Err(e) => error = error.max_stage(e),
+            }
+        }
+        if decoded.is_empty() { Err(error) } else { Ok(decoded) }
+    }
+
+    fn decode_at(&self, finders: [FinderPattern; 3]) -> Result<DecodedQr, DecodeError> {
+        let [top_left, top_right, bottom_left] = finders;
+        let across = (self.module_size_towards(&top_left, &top_right) + self.module_size_towards(&top_right, &top_left)) / 2.0;
+        let down = (self.module_size_towards(&top_left, &bottom_left) + self.module_size_towards(&bottom_left, &top_left)) / 2.0;
+        let module_size = (across + down) / 2.0;
+        let modules = (top_left.center.distance(top_right.center) / across + top_left.center.distance(bottom_left.center) / down) / 2.0 + 7.0;
+        let estimate = ((modules - 17.0) / 4.0).round() as i16;
+        let centers = finders.map(|finder| finder.center);
+
+        let mut error = DecodeError::NotFound;
+        for number in [estimate, estimate + 1, estimate - 1].into_iter().filter(|number| (MIN_VERSION..=MAX_VERSION).contains(number)) {
+            match self.decode_version(centers, module_size, number, true) {
+                Ok(decoded) => return Ok(decoded),
+                Err(e) => error = error.max_stage(e),
+            }
+        }
+        Err(error)
+    }
+
+    // samples and decodes the symbol as the given version. With `resample`,
+    // version information saying otherwise is trusted over the estimate.
+    fn decode_version(&self, finders: [Point; 3], module_size: f64, number: i16, resample: bool) -> Result<DecodedQr, DecodeError> {
+        let dimension = Version::Normal(number).width() as usize;
+        let mut alignments: Vec<Option<Point>> = Vec::new();
+        if number > 1 {
+            alignments.extend(self.alignment_candidates(finders, module_size, dimension).into_iter().map(Some));
+        }
+        alignments.push(None);
+
+        let mut error = DecodeError::NotFound;
+        for alignment in alignments {
+            let Some(transform) = self.transform(finders, alignment, dimension) else {
+                continue;
+            };
+            let grid = self.sample(&transform, dimension);
+            let result = match grid.version() {
+                Some(actual) if actual != number && resample => return self.decode_version(finders, module_size, actual, false),
+                _ => grid.decode(),
+            };
+            match result {
+                Ok(decoded) => return Ok(decoded),
+                Err(e) => error = error.max_stage(e),
+            }
+        }
+        Err(error)
+    }
+
+    // reads a Micro QR code around a single finder pattern, turned any
+    // multiple of 90 degrees; its format information gives its size
+    fn decode_micro_at(&self, finder: &FinderPattern) -> Result<DecodedQr, DecodeError> {
+        let largest = Version::Micro(4).width() as usize;
+        let rotations = [((1.0, 0.0), (0.0, 1.0)), ((0.0, 1.0), (-1.0, 0.0)), ((-1.0, 0.0), (0.0, -1.0)), ((0.0, -1.0), (1.0, 0.0))];
+        let mut error = DecodeError::NotFound;
+        for (across, down) in rotations {
+            let corner = |u: f64, v: f64| {
+                let (du, dv) = ((u - 3.5) * finder.module_size, (v - 3.5) * finder.module_size);
+                (Point { x: u, y: v }, Point {
+                    x: finder.center.x + du * across.0 + dv * down.0,
+                    y: finder.center.y + du * across.1 + dv * down.1,
+                })
+            };
+            let far = largest as f64;
+            let Some(transform) = Perspective::from_points([corner(0.0, 0.0), corner(far, 0.0), corner(0.0, far), corner(far, far)]) else {
+                continue;
+            };
+            match self.sample(&transform, largest).decode_micro() {
+                Ok(decoded) => return Ok(decoded),
+                Err(e) => error = error.max_stage(e),
+            }
+        }
+        Err(error)
+    }
+}
+
+/// A projective transform from module coordinates to pixels.
+pub(super) struct Perspective([f64; 8]);
+
+impl Perspective {
+    // the transform taking each `from` point to its `to` point
+    pub(super) fn from_points(points: [(Point, Point); 4]) -> Option<Perspective> {
+        let mut rows = [[0.0; 9]; 8];
+        for (i, (from, to)) in points.into_iter().enumerate() {
+            let (u, v) = (from.x, from.y);
+            rows[2 * i] = [u, v, 1.0, 0.0, 0.0, 0.0, -u * to.x, -v * to.x, to.x];
+            rows[2 * i + 1] = [0.0, 0.0, 0.0, u, v, 1.0, -u * to.y, -v * to.y, to.y];
+        }
+
+        // Gaussian elimination with partial pivoting
+        for column in 0..8 {
+            let pivot = (column..8).max_by(|&a, &b| rows[a][column].abs().total_cmp(&rows[b][column].abs()))?;
+            if rows[pivot][column].abs() < 1e-9 {
+                return None;
+            }
+            rows.swap(column, pivot);
+            for row in 0..8 {
+                if row != column {
+                    let pivot_row = rows[column];
+                    let factor = rows[row][column] / pivot_row[column];
+                    for (value, pivot_value) in rows[row].iter_mut().zip(pivot_row).skip(column) {
+                        *value -= factor * pivot_value;
+                    }
+                }
+            }
+        }
+        Some(Perspective(std::array::from_fn(|i| rows[i][8] / rows[i][i])))
+    }
+
+    pub(super) fn map(&self, point: Point) -> Point {
+        let [a, b, c, d, e, f, g, h] = self.0;
+        let w = g * point.x + h * point.y + 1.0;
+        Point { x: (a * point.x + b * point.y + c) / w, y: (d * point.x + e * point.y + f) / w }
+    }
+}
+
+/// The sampled modules of a symbol, before any unmasking.
+struct Grid {
+    size: usize,
+    dark: Vec<bool>,
+}
+
+// the positions of the format information bits, most significant first, next
+// to the top-left finder pattern and split between the other two
+fn format_coordinates(size: usize) -> [[(usize, usize); 15]; 2] {
+    let main = [(0, 8), (1, 8), (2, 8), (3, 8), (4, 8), (5, 8), (7, 8), (8, 8), (8, 7), (8, 5), (8, 4), (8, 3), (8, 2), (8, 1), (8, 0)];
+    let side = std::array::from_fn(|i| if i < 7 { (8, size - 1 - i) } else { (size - 15 + i, 8) });
+    [main, side]
+}
+
+// the positions of the Micro QR format information bits, most significant
+// first, below and right of the finder pattern
+fn micro_format_coordinates() -> [(usize, usize); 15] {
+    std::array::from_fn(|i| if i < 8 { (i + 1, 8) } else { (8, 15 - i) })
+}
+
+// the positions of the version information bits, most significant first,
+// above the bottom-left finder pattern and left of the top-right one
+fn version_coordinates(size: usize) -> [[(usize, usize); 18]; 2] {
+    let bottom_left = std::array::from_fn(|i| (5 - i / 3, size - 9 - i % 3));
+    [bottom_left, bottom_left.map(|(x, y)| (y, x))]
+}
+
+// `data` followed by its BCH check bits for `generator`
+fn bch_code(data: u32, generator: u32) -> u32 {
+    let degree = 31 - generator.leading_zeros();
+    let mut remainder = data << degree;
+    while remainder >= 1 << degree {
+        remainder ^= generator << (31 - remainder.leading_zeros() - degree);
+    }
+    data << degree | remainder
+}
+
+// the closest valid code to `bits` among `candidates`, if within the three
+// bit errors a BCH code can correct
+fn closest<T: Copy>(bits: &[u32], candidates: impl Iterator<Item = (u32, T)>) -> Option<T> {
+    candidates
+        .flat_map(|(code, value)| bits.iter().map(move |&read| ((read ^ code).count_ones(), value)))
+        .filter(|&(distance, _)| distance <= 3)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, value)| value)
+}
+
+// `qrcode` keeps its mask functions private, so they are repeated here
+fn is_masked(mask: u8, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (y / 2 + x / 3).is_multiple_of(2),
+        5 => (x * y) % 2 + (x * y) % 3 == 0,
+        6 => ((x * y) % 2 + (x * y) % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + (x * y) % 3).is_multiple_of(2),
+    }
+}
+
+// `qrcode` keeps its block table private, so the order it interleaves data
+// codewords in is recovered by interleaving their own indices. Returns the
+// index into the data of each interleaved codeword, and the start of each block.
+fn data_order(version: Version, ec_level: EcLevel) -> Option<(Vec<usize>, Vec<usize>)> {
+    let len = Bits::new(version).max_len(ec_level).ok()? / 8;
+    let low: Vec<u8> = (0..len).map(|i| i as u8).collect();
+    let high: Vec<u8> = (0..len).map(|i| (i >> 8) as u8).collect();
+    let (low, _) = construct_codewords(&low, version, ec_level).ok()?;
+    let (high, _) = construct_codewords(&high, version, ec_level).ok()?;
+    let order: Vec<usize> = low.iter().zip(&high).map(|(&low, &high)| (high as usize) << 8 | low as usize).collect();
+    // the first codeword of every block comes before the second of the first
+    let blocks = order.iter().position(|&index| index == 1).unwrap_or(1);
+    let starts = order[..blocks].to_vec();
+    Some((order, starts))
+}
+
+impl Grid {
+    fn get(&self, (x, y): (usize, usize)) -> bool {
+        self.dark[y * self.size + x]
+    }
+
+    fn read(&self, coordinates: &[(usize, usize)]) -> u32 {
+        coordinates.iter().fold(0, |bits, &position| bits << 1 | self.get(position) as u32)
+    }
+
+    fn transposed(&self) -> Grid {
+        let dark = (0..self.dark.len()).map(|i| self.get((i / self.size, i % self.size))).collect();
+        Grid { size: self.size, dark }
+    }
+
+    // the version the version information gives, for codes large enough to have it
+    fn version(&self) -> Option<i16> {
+        if self.size < Version::Normal(7).width() as usize {
+            return None;
+        }
+        let bits = version_coordinates(self.size).map(|coordinates| self.read(&coordinates));
+        closest(&bits, (7..=MAX_VERSION).map(|number| (bch_code(number as u32, 0x1f25), number)))
+    }
+
+    fn format(&self) -> Option<(EcLevel, u8)> {
+        let bits = format_coordinates(self.size).map(|coordinates| self.read(&coordinates));
+        let formats = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H].into_iter()
+            .flat_map(|ec_level| (0..8).map(move |mask| (ec_level, mask)))
+            .map(|(ec_level, mask)| (bch_code((ec_level as u32 ^ 1) << 3 | mask as u32, 0x537) ^ 0x5412, (ec_level, mask)));
+        closest(&bits, formats)
+    }
+
+    // the Micro QR version, EC level and mask the format information gives,
+    // if the timing patterns show this is a Micro QR code of that size
+    fn micro_format(&self) -> Option<(i16, EcLevel, u8)> {
+        let bits = self.read(&micro_format_coordinates());
+        let formats = (0..32u32).map(|format| (bch_code(format, 0x537) ^ 0x4445, format));
+        let format = closest(&[bits], formats)?;
+        let (number, ec_level) = MICRO_SYMBOLS[format as usize >> 2];
+
+        // each timing pattern alternates from the finder to the far edge,
+        // which sets a Micro QR code apart from a QR finder pattern
+        let size = Version::Micro(number).width() as usize;
+        let timing_errors = |position: fn(usize) -> (usize, usize)| {
+            (8..size).filter(|&i| self.get(position(i)) != (i % 2 == 0)).count()
+        };
+        let aligned = timing_errors(|i| (i, 0)) <= 1 && timing_errors(|i| (0, i)) <= 1;
+        aligned.then_some((number, ec_level, format as u8 & 3))
+    }
+
+    // the top left `size` modules
+    fn cropped(&self, size: usize) -> Grid {
+        let dark = (0..size * size).map(|i| self.get((i % size, i / size))).collect();
+        Grid { size, dark }
+    }
+
+    fn decode_micro(&self) -> Result<DecodedQr, DecodeError> {
+        // a mirrored code reads as its transpose
+        let mut error = DecodeError::UnreadableFormat;
+        for grid in [self, &self.transposed()] {
+            if let Some((number, ec_level, mask)) = grid.micro_format() {
+                let size = Version::Micro(number).width() as usize;
+                match grid.cropped(size).decode_micro_with(number, ec_level, mask) {
+                    Ok(decoded) => return Ok(decoded),
+                    Err(e) => error = error.max_stage(e),
+                }
+            }
+        }
+        Err(error)
+    }
+
+    // Micro QR codes have a single block, and in M1 and M3 the last data
+    // codeword is only 4 bits
+    fn decode_micro_with(&self, number: i16, ec_level: EcLevel, mask: u8) -> Result<DecodedQr, DecodeError> {
+        let version = Version::Micro(number);
+        let bits = self.data_modules(version, |x, y| is_masked(MICRO_MASKS[mask as usize], x, y));
+        let data_bits = Bits::new(version).max_len(ec_level).map_err(|_| DecodeError::UnreadableFormat)?;
+        let value = |bits: &[bool]| bits.iter().fold(0u16, |value, &bit| value << 1 | bit as u16);
+
+        let mut codewords: Vec<u16> = bits[..data_bits].chunks(8).map(|bits| value(bits) << (8 - bits.len())).collect();
+        let data_len = codewords.len();
+        codewords.extend(bits[data_bits..].chunks_exact(8).map(value));
+        let ec_len = codewords.len() - data_len;
+        let corrected = GaloisField::new(8, 0x11d).correct(&mut codewords, ec_len, 0).ok_or(DecodeError::TooManyErrors)?;
+
+        let data: Vec<u8> = codewords[..data_len].iter().map(|&codeword| codeword as u8).collect();
+        let mut decoded = parse(&data, version).ok_or(DecodeError::InvalidData)?;
+        decoded.ec_level = ec_level;
+        decoded.mask = mask;
+        decoded.corrected = corrected;
+        Ok(decoded)
+    }
+
+    // the data modules, unmasked, in placement order: upwards and downwards
+    // in two module wide columns from the right, skipping the timing pattern
+    fn data_modules(&self, version: Version, is_masked: impl Fn(usize, usize) -> bool) -> Vec<bool> {
+        let width = self.size as i16;
+        let is_version_information = |x: usize, y: usize| {
+            matches!(version, Version::Normal(7..))
+                && ((x < 6 && y + 11 >= self.size && y + 8 < self.size) || (y < 6 && x + 11 >= self.size && x + 8 < self.size))
+        };
+
+        let mut bits = Vec::new();
+        let mut right = self.size - 1;
+        let mut upwards = true;
+        loop {
+            for i in 0..self.size {
+                let y = if upwards { self.size - 1 - i } else { i };
+                for x in [right, right - 1] {
+                    if !is_functional(version, width, x as i16, y as i16) && !is_version_information(x, y) {
+                        bits.push(self.get((x, y)) ^ is_masked(x, y));
+                    }
+                }
+            }
+            upwards = !upwards;
+            // QR codes end on column 1, past the timing pattern in column 6;
+            // Micro QR codes on column 2, by the one in column 0
+            match right {
+                1 | 2 => break,
+                8 if !version.is_micro() => right = 5,
+                _ => right -= 2,
+            }
+        }
+        bits
+    }
+
+    fn decode(&self) -> Result<DecodedQr, DecodeError> {
+        // a mirrored code reads as its transpose
+        let mut error = DecodeError::UnreadableFormat;
+        for grid in [self, &self.transposed()] {
+            if let Some(format) = grid.format() {
+                match grid.decode_with(format) {
+                    Ok(decoded) => return Ok(decoded),
+                    Err(e) => error = error.max_stage(e),
+                }
+            }
+        }
+        Err(error)
+    }
+
+    fn decode_with(&self, (ec_level, mask): (EcLevel, u8)) -> Result<DecodedQr, DecodeError> {
+        let number = ((self.size - 17) / 4) as i16;
+        let version = Version::Normal(number);
+        let bits = self.data_modules(version, |x, y| is_masked(mask, x, y));
+        let codewords: Vec<u8> = bits.chunks_exact(8)
+            .map(|byte| byte.iter().fold(0, |value, &bit| value << 1 | bit as u8))
+            .collect();
+
+        let (order, starts) = data_order(version, ec_level).ok_or(DecodeError::UnreadableFormat)?;
+        let blocks = starts.len();
+        let ec_len = (codewords.len() - order.len()) / blocks;
+        let mut data = vec![0; order.len()];
+        for (&index, &codeword) in order.iter().zip(&codewords) {
+            data[index] = codeword;
+        }
+
+        let field = GaloisField::new(8, 0x11d);
+        let mut corrected = 0;
+        for (block, &start) in starts.iter().enumerate() {
+            let end = starts.get(block + 1).copied().unwrap_or(order.len());
+            let mut block_codewords: Vec<u16> = data[start..end].iter().map(|&codeword| codeword as u16).collect();
+            block_codewords.extend((0..ec_len).map(|i| codewords[order.len() + i * blocks + block] as u16));
+            corrected += field.correct(&mut block_codewords, ec_len, 0).ok_or(DecodeError::TooManyErrors)?;
+            for (byte, &codeword) in data[start..end].iter_mut().zip(&block_codewords) {
+                *byte = codeword as u8;
+            }
+        }
+
+        let mut decoded = parse(&data, version).ok_or(DecodeError::InvalidData)?;
+        decoded.ec_level = ec_level;
+        decoded.mask = mask;
+        decoded.corrected = corrected;
+        Ok(decoded)
+    }
+}
+
+#[derive(Clone)]
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn remaining(&self) -> usize {
+        self.bytes.len() * 8 - self.position
+    }
+
+    fn read(&mut self, bits: usize) -> Option<u32> {
+        if bits > self.remaining() {
+            return None;
+        }
+        let value = (self.position..self.position + bits)
+            .fold(0, |value, bit| value << 1 | (self.bytes[bit / 8] >> (7 - bit % 8) & 1) as u32);
+        self.position += bits;
+        Some(value)
+    }
+}
+
+const ALPHANUMERIC: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+// the segments of the bit stream, up to the terminator. Micro QR has
+// shorter mode indicators and terminators, and no ECI, FNC1 or Structured
+// Append.
+fn parse(data: &[u8], version: Version) -> Option<DecodedQr> {
+    let mut reader = BitReader { bytes: data, position: 0 };
+    let mut decoded = DecodedQr {
+        version,
+        ec_level: EcLevel::L,
+        mask: 0,
+        payload: Vec::new(),
+        eci: None,
+        structured_append: None,
+        gs1: false,
+        kanji: false,
+        corrected: 0,
+    };
+    let terminator = match version {
+        Version::Micro(number) => number as usize * 2 + 1,
+        Version::Normal(_) => 4,
+    };
+
+    while reader.remaining() >= terminator && reader.clone().read(terminator)? != 0 {
+        let mode = match version {
+            Version::Micro(number) => match reader.read(number as usize - 1)? {
+                0 => Mode::Numeric,
+                1 => Mode::Alphanumeric,
+                2 => Mode::Byte,
+                _ => Mode::Kanji,
+            },
+            Version::Normal(_) => match reader.read(4)? {
+                0b0001 => Mode::Numeric,
+                0b0010 => Mode::Alphanumeric,
+                0b0100 => Mode::Byte,
+                0b1000 => Mode::Kanji,
+                0b0111 => {
+                    let first = reader.read(8)?;
+                    decoded.eci = Some(match first {
+                        _ if first & 0x80 == 0 => first,
+                        _ if first & 0xc0 == 0x80 => (first & 0x3f) << 8 | reader.read(8)?,
+                        _ if first & 0xe0 == 0xc0 => (first & 0x1f) << 16 | reader.read(16)?,
+                        _ => return None,
+                    });
+                    continue;
+                },
+                0b0011 => {
+                    let index = reader.read(4)? as u8;
+                    let total = reader.read(4)? as u8 + 1;
+                    let parity = reader.read(8)? as u8;
+                    decoded.structured_append = Some(StructuredAppend { index, total, parity });
+                    continue;
+                },
+                0b0101 => {
+                    decoded.gs1 = true;
+                    continue;
+                },
+                // FNC1 in second position, followed by an application indicator
+                0b1001 => {
+                    reader.read(8)?;
+                    continue;
+                },
+                _ => return None,
+            },
+        };
+
+        let count = reader.read(mode.length_bits_count(version))?;
+        match mode {
+            Mode::Numeric => {
+                let mut count = count;
+                while count > 0 {
+                    let (bits, digits) = match count {
+                        1 => (4, 1),
+                        2 => (7, 2),
+                        _ => (10, 3),
+                    };
+                    let value = reader.read(bits)?;
+                    let text = format!("{value:0digits$}");
+                    if text.len() != digits {
+                        return None;
+                    }
+                    decoded.payload.extend(text.bytes());
+                    count -= digits as u32;
+                }
+            },
+            Mode::Alphanumeric => {
+                let mut text = Vec::new();
+                for _ in 0..count / 2 {
+                    let value = reader.read(11)? as usize;
+                    text.extend([*ALPHANUMERIC.get(value / 45)?, ALPHANUMERIC[value % 45]]);
+                }
+                if count % 2 == 1 {
+                    text.push(*ALPHANUMERIC.get(reader.read(6)? as usize)?);
+                }
+                if decoded.gs1 {
+                    // in GS1 data, % stands for FNC1 and %% for a literal %
+                    let mut characters = text.into_iter().peekable();
+                    while let Some(character) = characters.next() {
+                        match character {
+                            b'%' if characters.next_if_eq(&b'%').is_some() => decoded.payload.push(b'%'),
+                            b'%' => decoded.payload.push(0x1d),
+                            _ => decoded.payload.push(character),
+                        }
+                    }
+                } else {
+                    decoded.payload.extend(text);
+                }
+            },
+            Mode::Byte => {
+                for _ in 0..count {
+                    decoded.payload.push(reader.read(8)? as u8);
+                }
+            },
+            Mode::Kanji => {
+                decoded.kanji = true;
+                for _ in 0..count {
+                    let value = reader.read(13)?;
+                    let packed = ((value / 0xc0) << 8) | (value % 0xc0);
+                    let shift_jis = if packed + 0x8140 <= 0x9ffc { packed + 0x8140 } else { packed + 0xc140 };
+                    decoded.payload.extend([(shift_jis >> 8) as u8, shift_jis as u8]);
+                }
+            },
+        }
+    }
+    Some(decoded)
+}
+
+// whether `point`, other than the patterns' own centres, lies within the
+// symbol the three finder patterns outline
+fn encloses(finders: [FinderPattern; 3], point: Point) -> bool {
+    let [top_left, top_right, bottom_left] = finders.map(|finder| finder.center);
+    if [top_left, top_right, bottom_left].contains(&point) {
+        return false;
+    }
+    // `point` as top_left + u * across + v * down
+    let across = Point { x: top_right.x - top_left.x, y: top_right.y - top_left.y };
+    let down = Point { x: bottom_left.x - top_left.x, y: bottom_left.y - top_left.y };
+    let (dx, dy) = (point.x - top_left.x, point.y - top_left.y);
+    let determinant = across.x * down.y - across.y * down.x;
+    let u = (dx * down.y - dy * down.x) / determinant;
+    let v = (across.x * dy - across.y * dx) / determinant;
+    // the symbol reaches 3.5 modules past the finder pattern centres
+    let margin_u = 3.5 * finders[0].module_size / top_left.distance(top_right);
+    let margin_v = 3.5 * finders[0].module_size / top_left.distance(bottom_left);
+    (-margin_u..=1.0 + margin_u).contains(&u) && (-margin_v..=1.0 + margin_v).contains(&v)
+}
+
+// every plausible (top-left, top-right, bottom-left) arrangement of three
+// finder patterns, the most right-angled and evenly sized first
+fn finder_triples(finders: &[FinderPattern]) -> Vec<[FinderPattern; 3]> {
+    let mut triples = Vec::new();
+    for (i, a) in finders.iter().enumerate() {
+        for (j, b) in finders.iter().enumerate().skip(i + 1) {
+            for c in finders.iter().skip(j + 1) {
+                let sizes = [a.module_size, b.module_size, c.module_size];
+                let (smallest, largest) = (sizes.iter().copied().fold(f64::MAX, f64::min), sizes.iter().copied().fold(0.0, f64::max));
+                if largest > smallest * 1.5 {
+                    continue;
+                }
+
+                // the top-left pattern is opposite the longest side
+                let mut corners = [(a, b.center.distance(c.center)), (b, a.center.distance(c.center)), (c, a.center.distance(b.center))];
+                corners.sort_by(|x, y| y.1.total_cmp(&x.1));
+                let [(top_left, hypotenuse), (p, leg_q), (q, leg_p)] = corners;
+                if leg_p.min(leg_q) < top_left.module_size * 14.0 {
+                    continue;
+                }
+                let skew = (leg_p - leg_q).abs() / leg_p.max(leg_q)
+                    + (hypotenuse.powi(2) - leg_p.powi(2) - leg_q.powi(2)).abs() / hypotenuse.powi(2);
+                if skew > 0.5 {
+                    continue;
+                }
+
+                // with y pointing down, top-right then bottom-left turns clockwise
+                let (o, u, v) = (top_left.center, p.center, q.center);
+                let cross = (u.x - o.x) * (v.y - o.y) - (u.y - o.y) * (v.x - o.x);
+                let triple = if cross > 0.0 { [*top_left, *p, *q] } else { [*top_left, *q, *p] };
+                triples.push((skew, triple));
+            }
+        }
+    }
+    triples.sort_by(|a, b| a.0.total_cmp(&b.0));
+    triples.into_iter().map(|(_, triple)| triple).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{capacity, DEFAULT_MODULE_SIZE};
+
+    fn render(code: &GeneratedQr) -> GrayImage {
+        render_at(code, DEFAULT_MODULE_SIZE)
+    }
+
+    fn render_at(code: &GeneratedQr, module_size: u32) -> GrayImage {
+        image::DynamicImage::ImageRgb8(code.to_image(module_size)).to_luma8()
+    }
+
+    #[test]
+    fn kanji_reads_as_shift_jis() {
+        let options = QrOptions { kanji_mode: true, ..QrOptions::default() };
+        let code = QrRequest::new("日本語のテキスト", options).generate().unwrap();
+        let decoded = decode(&render(&code)).unwrap();
+        assert!(decoded.kanji);
+        assert_eq!(decoded.text().as_deref(), Some("日本語のテキスト"));
+        assert_eq!(decoded.to_request(QrOptions::default()).data, "日本語のテキスト".as_bytes());
+    }
+
+    #[test]
+    fn micro_qr_round_trips_every_version_level_and_mask() {
+        for (number, ec_level) in MICRO_SYMBOLS {
+            for mask in 0..4 {
+                let options = QrOptions {
+                    symbology: Symbology::MicroQr,
+                    ec_level,
+                    min_version: number,
+                    max_version: number,
+                    mask: Some(mask),
+                    ..QrOptions::default()
+                };
+                let code = QrRequest::new("12", options).generate().unwrap();
+                let decoded = decode(&render(&code)).unwrap();
+                assert_eq!((decoded.version, decoded.ec_level, decoded.mask), (Version::Micro(number), ec_level, mask));
+                assert_eq!(decoded.payload, b"12");
+                assert_eq!(code.verify(DEFAULT_MODULE_SIZE), Ok(()));
+            }
+        }
+    }
+
+    #[test]
+    fn micro_qr_terminator_inside_the_last_half_codeword() {
+        let payload = b"\x10eAT:\x00";
+        let options = QrOptions { symbology: Symbology::MicroQr, ec_level: EcLevel::M, max_version: 4, ..QrOptions::default() };
+        let code = QrRequest::new(payload.to_vec(), options).generate().unwrap();
+        let decoded = decode(&render(&code)).unwrap();
+        assert_eq!((decoded.version, decoded.ec_level), (Version::Micro(3), EcLevel::M));
+        assert_eq!(decoded.payload, payload);
+    }
+
+    #[test]
+    fn qr_round_trips_across_versions_levels_and_masks() {
+        let ec_levels = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H];
+        for (index, number) in [1, 2, 5, 7, 10, 14, 22, 27, 40].into_iter().enumerate() {
+            for (level, ec_level) in ec_levels.into_iter().enumerate() {
+                let mask = ((index * 4 + level) % 8) as u8;
+                // fills the symbol, so every block holds data
+                let limit = capacity::character_limit(Version::Normal(number), ec_level, Mode::Byte, false);
+                let data: Vec<u8> = (0..limit).map(|i| (i * 131 % 251) as u8).collect();
+                let options = QrOptions { ec_level, min_version: number, max_version: number, mask: Some(mask), ..QrOptions::default() };
+                let code = QrRequest::new(data.clone(), options).generate().unwrap();
+
+                let decoded = decode(&render_at(&code, 3)).unwrap();
+                assert_eq!((decoded.version, decoded.ec_level, decoded.mask), (Version::Normal(number), ec_level, mask));
+                assert_eq!(decoded.payload, data);
+                assert_eq!(decoded.corrected, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn every_mask_round_trips() {
+        for mask in 0..8 {
+            let options = QrOptions { mask: Some(mask), ..QrOptions::default() };
+            let code = QrRequest::new("HELLO 12345 héllo", options).generate().unwrap();
+            let decoded = decode(&render(&code)).unwrap();
+            assert_eq!(decoded.mask, mask);
+            assert_eq!(decoded.payload, "HELLO 12345 héllo".as_bytes());
+        }
+    }
+
+    #[test]
+    fn repairs_damaged_modules() {
+        let options = QrOptions { ec_level: EcLevel::H, min_version: 5, max_version: 5, ..QrOptions::default() };
+        let code = QrRequest::new("damaged but still readable", options).generate().unwrap();
+        let mut image = render(&code);
+        // a smudge over data modules left of the bottom-right alignment pattern
+        let module = DEFAULT_MODULE_SIZE;
+        let offset = code.quiet_zone() as u32 * module;
+        for y in offset + 20 * module..offset + 27 * module {
+            for x in offset + 12 * module..offset + 18 * module {
+                image.put_pixel(x, y, image::Luma([0]));
+            }
+        }
+        let decoded = decode(&image).unwrap();
+        assert!(decoded.corrected > 0);
+        assert_eq!(decoded.payload, b"damaged but still readable");
+    }
+}
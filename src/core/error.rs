@@ -0,0 +1,222 @@
+use std::fmt;
+
+use qrcode::types::{EcLevel, Mode, QrError};
+
+use super::segment::mode_name;
+use super::gs1::Gs1Error;
+use super::aztec::{MAX_EC_PERCENT, MIN_EC_PERCENT};
+use super::pdf417::{MAX_PDF417_COLUMNS, MAX_SECURITY_LEVEL};
+use super::{Charset, Symbology, MAX_SYMBOLS};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GenerateError {
+    /// The payload is longer than the largest allowed version holds.
+    DataTooLong {
+        symbology: Symbology,
+        max_version: i16,
+        ec_level: EcLevel,
+        /// The cheapest mode for the payload, which `length` and `limit` count in.
+        mode: Mode,
+        length: usize,
+        limit: usize,
+        /// The highest lower error correction level the payload would fit at.
+        fitting_ec_level: Option<EcLevel>,
+    },
+    /// The encoded payload is larger than the largest allowed symbol of a
+    /// symbology without versions holds. Both sizes count bytes, or
+    /// codewords for Data Matrix and PDF417.
+    SymbolTooLong { symbology: Symbology, length: usize, limit: usize },
+    /// No rMQR symbol has this height and width.
+    UnsupportedRmqrSize { height: Option<usize>, width: Option<usize> },
+    /// Aztec error correction is outside 5% to 95%.
+    InvalidEcPercent(u8),
+    /// PDF417 has 1 to 30 data columns.
+    InvalidPdf417Columns(usize),
+    /// PDF417 security levels go from 0 to 8.
+    InvalidSecurityLevel(u8),
+    /// Even a full Structured Append sequence cannot hold the payload.
+    TooLongForStructuredAppend,
+    /// None of the allowed versions can encode some character of the payload
+    /// (Micro QR M1 only holds digits, M2 only digits and uppercase letters).
+    UnsupportedCharacters { symbology: Symbology, min_version: i16, max_version: i16 },
+    /// None of the allowed versions offers the requested error correction level.
+    UnsupportedEcLevel { symbology: Symbology, ec_level: EcLevel, min_version: i16, max_version: i16 },
+    InvalidVersionRange { symbology: Symbology, min_version: i16, max_version: i16 },
+    InvalidMask(u8),
+    /// Manual segments leave gaps, overlap or run past the end of the data.
+    InvalidSegmentation,
+    /// A manual segment holds characters its mode cannot represent.
+    UnsupportedSegmentMode { index: usize, mode: Mode },
+    /// The symbology has no ECI mode (Micro QR and the linear barcodes).
+    EciNotSupported(Symbology),
+    /// Only text can be transcoded, but the payload is not valid UTF-8.
+    EciNotText,
+    UnencodableCharacter { charset: Charset, character: char },
+    Gs1(Gs1Error),
+    /// The symbology has no FNC1 mode (Micro QR, PDF417, EAN-13 and UPC-A).
+    Gs1NotSupported(Symbology),
+    /// EAN-13 and UPC-A hold a fixed number of digits, plus an optional check digit.
+    InvalidLength { symbology: Symbology, digits: usize },
+    /// The check digit at the end of EAN-13 or UPC-A data is wrong.
+    InvalidCheckDigit { symbology: Symbology, given: u8, expected: u8 },
+    /// Code 128 only holds ASCII.
+    UnsupportedByte { symbology: Symbology, byte: u8 },
+    Qr(QrError),
+}
+
+fn ec_level_name(ec_level: EcLevel) -> &'static str {
+    match ec_level {
+        EcLevel::L => "L (7%)",
+        EcLevel::M => "M (15%)",
+        EcLevel::Q => "Q (25%)",
+        EcLevel::H => "H (30%)",
+    }
+}
+
+// e.g. "a version 5 QR code" or "any Micro QR code from M1 to M3"
+fn describe_versions(symbology: Symbology, min_version: i16, max_version: i16) -> String {
+    let min_name = symbology.version_name(min_version);
+    match symbology {
+        Symbology::Qr if min_version == max_version => format!("a version {min_name} {symbology}"),
+        Symbology::MicroQr if min_version == max_version => format!("an {min_name} {symbology}"),
+        Symbology::Qr => format!("any {symbology} from version {min_name} to {}", symbology.version_name(max_version)),
+        Symbology::MicroQr => format!("any {symbology} from {min_name} to {}", symbology.version_name(max_version)),
+        Symbology::Rmqr => format!("an {symbology}"),
+        _ => format!("a {symbology}"),
+    }
+}
+
+// 2331 -> "2,331"
+fn group_digits(n: usize) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            GenerateError::DataTooLong { symbology, max_version, ec_level, mode, length, limit, fitting_ec_level } => {
+                let unit = match mode {
+                    Mode::Numeric => "digits",
+                    Mode::Alphanumeric => "alphanumeric characters",
+                    Mode::Byte => "bytes",
+                    Mode::Kanji => "Kanji characters",
+                };
+                let versions = describe_versions(symbology, max_version, max_version);
+                let level = ec_level_name(ec_level);
+                write!(f, "Data is too large! It is {} {unit}, but {versions} holds at most {} at error correction level {level}. ", group_digits(length), group_digits(limit))?;
+                match fitting_ec_level {
+                    Some(fitting) => write!(f, "It would fit at error correction level {}.", ec_level_name(fitting)),
+                    None if max_version < symbology.max_version() => write!(f, "Allow a larger version or shorten the data."),
+                    None if symbology == Symbology::MicroQr => write!(f, "Use a regular QR code or shorten the data."),
+                    None => write!(f, "No error correction level is low enough, so the data has to be shortened."),
+                }
+            },
+            GenerateError::SymbolTooLong { symbology, length, limit } => {
+                // a PDF417 codeword is a base 929 digit, not a byte
+                let unit = if symbology == Symbology::Pdf417 { "codewords" } else { "bytes" };
+                write!(f, "Data is too large! Encoded, it takes {} {unit}, but the largest {symbology} with these options holds {}.", group_digits(length), group_digits(limit))
+            },
+            GenerateError::UnsupportedRmqrSize { height, width } => {
+                let size = match (height, width) {
+                    (Some(height), Some(width)) => format!("{height} modules high and {width} wide"),
+                    (Some(height), None) => format!("{height} modules high"),
+                    (None, Some(width)) => format!("{width} modules wide"),
+                    (None, None) => String::from("of this size"),
+                };
+                write!(f, "No rMQR code is {size}. Heights go from 7 to 17 in steps of 2, widths are 27, 43, 59, 77, 99 or 139, and only heights 11 and 13 come 27 wide.")
+            },
+            GenerateError::InvalidEcPercent(percent) => {
+                write!(f, "Error correction must be between {MIN_EC_PERCENT}% and {MAX_EC_PERCENT}% of the symbol, not {percent}%.")
+            },
+            GenerateError::InvalidPdf417Columns(columns) => {
+                write!(f, "PDF417 has 1 to {MAX_PDF417_COLUMNS} data columns, not {columns}.")
+            },
+            GenerateError::InvalidSecurityLevel(level) => {
+                write!(f, "PDF417 security levels go from 0 to {MAX_SECURITY_LEVEL}, not {level}.")
+            },
+            GenerateError::TooLongForStructuredAppend => {
+                write!(f, "Data is too large, even when split across {MAX_SYMBOLS} linked QR codes.")
+            },
+            GenerateError::UnsupportedCharacters { symbology, min_version, max_version } => {
+                let versions = describe_versions(symbology, min_version, max_version);
+                write!(f, "Data cannot be encoded in {versions}: M1 only holds digits and M2 only digits, uppercase letters and \" $%*+-./:\". Allow M3 or M4 for other text.")
+            },
+            GenerateError::UnsupportedEcLevel { symbology, ec_level, min_version, max_version } => {
+                let versions = describe_versions(symbology, min_version, max_version);
+                let level = ec_level_name(ec_level);
+                match symbology {
+                    Symbology::Rmqr => write!(f, "Error correction level {level} is not available in {versions}, which only has M (15%) and H (30%)."),
+                    _ => write!(f, "Error correction level {level} is not available in {versions}. Micro QR M1 only detects errors, M2 and M3 go up to M (15%) and M4 up to Q (25%)."),
+                }
+            },
+            GenerateError::InvalidVersionRange { symbology, min_version, max_version } => {
+                let (first, last) = (symbology.version_name(symbology.min_version()), symbology.version_name(symbology.max_version()));
+                write!(f, "Invalid version range {min_version} to {max_version}. Versions go from {first} to {last} and the smallest may not exceed the largest.")
+            },
+            GenerateError::InvalidMask(mask) => {
+                write!(f, "Mask {mask} does not exist for this kind of QR code.")
+            },
+            GenerateError::InvalidSegmentation => {
+                write!(f, "The segments do not cover the data exactly. Reset the segmentation to automatic.")
+            },
+            GenerateError::UnsupportedSegmentMode { index, mode } => {
+                let mode = mode_name(mode);
+                write!(f, "Segment {} contains characters that cannot be encoded in {mode} mode.", index + 1)
+            },
+            GenerateError::EciNotSupported(Symbology::MicroQr) => {
+                write!(f, "Micro QR codes cannot declare a character set. Turn off ECI or use a regular QR code.")
+            },
+            GenerateError::EciNotSupported(symbology) => {
+                write!(f, "{symbology} cannot declare a character set. Turn off ECI.")
+            },
+            GenerateError::EciNotText => {
+                write!(f, "Only text can be converted to another character set, but this data is not UTF-8 text. Turn off ECI to encode it as is.")
+            },
+            GenerateError::UnencodableCharacter { charset, character } => {
+                write!(f, "'{character}' cannot be written in {charset}. Pick another character set, such as UTF-8.")
+            },
+            GenerateError::Gs1(ref e) => write!(f, "{e}"),
+            GenerateError::Gs1NotSupported(Symbology::MicroQr) => {
+                write!(f, "Micro QR codes cannot hold GS1 element strings. Use a regular QR code or a Digital Link.")
+            },
+            GenerateError::Gs1NotSupported(Symbology::Pdf417) => {
+                write!(f, "PDF417 has no FNC1, so it cannot hold GS1 element strings. Use a Digital Link instead.")
+            },
+            GenerateError::Gs1NotSupported(symbology) => {
+                write!(f, "{symbology} cannot hold GS1 element strings. Use Code 128 (GS1-128) or a QR code.")
+            },
+            GenerateError::InvalidLength { symbology, digits } => {
+                write!(f, "{symbology} holds exactly {digits} digits, optionally followed by their check digit.")
+            },
+            GenerateError::InvalidCheckDigit { symbology, given, expected } => {
+                write!(f, "The check digit of this {symbology} should be {expected}, not {given}. Correct it, or leave it off to have it calculated.")
+            },
+            GenerateError::UnsupportedByte { symbology, byte } => {
+                write!(f, "{symbology} only holds ASCII characters, but the data contains byte 0x{byte:02x}.")
+            },
+            GenerateError::Qr(e) => write!(f, "An error has occured. ({e:?})"),
+        }
+    }
+}
+
+impl std::error::Error for GenerateError {}
+
+impl From<QrError> for GenerateError {
+    fn from(e: QrError) -> Self {
+        GenerateError::Qr(e)
+    }
+}
+
+impl From<Gs1Error> for GenerateError {
+    fn from(e: Gs1Error) -> Self {
+        GenerateError::Gs1(e)
+    }
+}
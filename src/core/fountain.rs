@@ -0,0 +1,376 @@
+//! Fountain-coded streams: a file too large for one symbol is cut into
+//! blocks, and each frame of a looping animation carries the XOR of a few of
+//! them. A receiver can rebuild the file from any sufficient set of frames,
+//! in any order, so missed frames only cost waiting for more.
+//!
+//! The first frames hold each block on its own; the rest follow an LT code
+//! with a robust soliton degree distribution. Every frame starts with:
+//!
+//! | bytes | contents                                      |
+//! |-------|-----------------------------------------------|
+//! | 3     | `QPF`                                         |
+//! | 4     | Adler-32 of the file, which names the stream  |
+//! | 4     | file size                                     |
+//! | 2     | block size                                    |
+//! | 4     | frame number, which seeds the block choice    |
+//! | 2     | number of blocks in the frame                 |
+//!
+//! followed by one block's worth of data. All numbers are big-endian.
+
+use std::fmt;
+
+const MAGIC: &[u8; 3] = b"QPF";
+const HEADER_LEN: usize = 19;
+
+/// Bytes of the file carried by each frame unless the caller asks otherwise.
+pub const DEFAULT_BLOCK_SIZE: usize = 200;
+pub const MIN_BLOCK_SIZE: usize = 16;
+pub const MAX_BLOCK_SIZE: usize = 2048;
+/// Extra frames beyond one per block, as a percentage of the block count.
+pub const DEFAULT_REDUNDANCY_PERCENT: usize = 50;
+
+// robust soliton parameters
+const SOLITON_C: f64 = 0.1;
+const SOLITON_DELTA: f64 = 0.5;
+
+// SplitMix64, so the encoder and every decoder pick the same blocks for a
+// frame without depending on a platform's random number generator
+pub(super) struct Rng(u64);
+
+impl Rng {
+    pub(super) fn new(checksum: u32, seed: u32) -> Rng {
+        Rng((checksum as u64) << 32 | seed as u64)
+    }
+
+    pub(super) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+// the cumulative robust soliton distribution over degrees 1..=blocks
+fn degree_distribution(blocks: usize) -> Vec<f64> {
+    let k = blocks as f64;
+    let r = SOLITON_C * (k / SOLITON_DELTA).ln() * k.sqrt();
+    let spike = ((k / r).floor() as usize).clamp(1, blocks);
+    let mut weights: Vec<f64> = (1..=blocks)
+        .map(|d| {
+            let ideal = if d == 1 { 1.0 / k } else { 1.0 / (d * (d - 1)) as f64 };
+            let robust = match d {
+                d if d < spike => r / (d as f64 * k),
+                d if d == spike => r * (r / SOLITON_DELTA).ln() / k,
+                _ => 0.0,
+            };
+            ideal + robust.max(0.0)
+        })
+        .collect();
+    let total: f64 = weights.iter().sum();
+    let mut sum = 0.0;
+    for weight in &mut weights {
+        sum += *weight / total;
+        *weight = sum;
+    }
+    weights
+}
+
+// the blocks XORed into a frame; the degree travels in the frame, so only
+// this integer-only choice has to agree between encoder and decoder
+fn frame_blocks(checksum: u32, seed: u32, degree: usize, blocks: usize) -> Vec<usize> {
+    if degree == 1 && (seed as usize) < blocks {
+        return vec![seed as usize];
+    }
+    let mut rng = Rng::new(checksum, seed);
+    let mut chosen = Vec::with_capacity(degree);
+    while chosen.len() < degree.min(blocks) {
+        let block = rng.below(blocks);
+        if !chosen.contains(&block) {
+            chosen.push(block);
+        }
+    }
+    chosen
+}
+
+// Adler-32
+fn checksum(data: &[u8]) -> u32 {
+    const MODULUS: u32 = 65521;
+    let (a, b) = data.iter().fold((1, 0), |(a, b), &byte| {
+        let a = (a + byte as u32) % MODULUS;
+        (a, (b + a) % MODULUS)
+    });
+    b << 16 | a
+}
+
+/// Number of blocks a file of `size` bytes is cut into.
+pub fn block_count(size: usize, block_size: usize) -> usize {
+    size.div_ceil(block_size).max(1)
+}
+
+/// Number of frames to send for a file of `size` bytes.
+pub fn frame_count(size: usize, block_size: usize, redundancy_percent: usize) -> usize {
+    let blocks = block_count(size, block_size);
+    blocks + (blocks * redundancy_percent).div_ceil(100)
+}
+
+/// Cuts `data` into `frames` frame payloads of `block_size` bytes of data each.
+pub fn encode(data: &[u8], block_size: usize, frames: usize) -> Vec<Vec<u8>> {
+    let blocks = block_count(data.len(), block_size);
+    let checksum = checksum(data);
+    let block = |index: usize| {
+        let mut block = data.get(index * block_size..).unwrap_or_default().to_vec();
+        block.resize(block_size, 0);
+        block
+    };
+    let distribution = degree_distribution(blocks);
+
+    (0..frames as u32)
+        .map(|seed| {
+            let degree = if (seed as usize) < blocks {
+                1
+            } else {
+                // an independent draw from the frame's own generator
+                let mut rng = Rng::new(!checksum, seed);
+                let sample = (rng.next() >> 11) as f64 / (1u64 << 53) as f64;
+                distribution.iter().position(|&sum| sample < sum).unwrap_or(blocks - 1) + 1
+            };
+
+            let mut frame = Vec::with_capacity(HEADER_LEN + block_size);
+            frame.extend_from_slice(MAGIC);
+            frame.extend_from_slice(&checksum.to_be_bytes());
+            frame.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            frame.extend_from_slice(&(block_size as u16).to_be_bytes());
+            frame.extend_from_slice(&seed.to_be_bytes());
+            frame.extend_from_slice(&(degree as u16).to_be_bytes());
+            let mut payload = vec![0; block_size];
+            for index in frame_blocks(checksum, seed, degree, blocks) {
+                xor(&mut payload, &block(index));
+            }
+            frame.extend(payload);
+            frame
+        })
+        .collect()
+}
+
+fn xor(into: &mut [u8], block: &[u8]) {
+    for (byte, other) in into.iter_mut().zip(block) {
+        *byte ^= other;
+    }
+}
+
+/// Whether `payload` looks like a frame of a fountain-coded stream.
+pub fn is_frame(payload: &[u8]) -> bool {
+    payload.starts_with(MAGIC)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FountainError {
+    /// The payload does not start with a stream frame header.
+    NotAFrame,
+    /// The frame is cut short or its header makes no sense.
+    Corrupt,
+    /// The frame belongs to a different stream than the frames before it.
+    OtherStream,
+    /// Every block was recovered, but the file does not match its checksum.
+    ChecksumMismatch,
+}
+
+impl fmt::Display for FountainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FountainError::NotAFrame => write!(f, "This is not a frame of an animated stream."),
+            FountainError::Corrupt => write!(f, "The frame is damaged or incomplete."),
+            FountainError::OtherStream => write!(f, "The frame belongs to a different file than the frames before it."),
+            FountainError::ChecksumMismatch => write!(f, "The reassembled file does not match its checksum."),
+        }
+    }
+}
+
+impl std::error::Error for FountainError {}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Stream {
+    checksum: u32,
+    size: usize,
+    block_size: usize,
+}
+
+/// Reassembles a file from the frames of its stream, fed in any order.
+#[derive(Default)]
+pub struct FountainDecoder {
+    stream: Option<Stream>,
+    blocks: Vec<Option<Vec<u8>>>,
+    // frames that still mix more than one unknown block
+    pending: Vec<(Vec<usize>, Vec<u8>)>,
+    seen: Vec<u32>,
+}
+
+impl FountainDecoder {
+    pub fn new() -> FountainDecoder {
+        FountainDecoder::default()
+    }
+
+    /// Adds a scanned frame, returning whether it was new to the decoder.
+    pub fn add_frame(&mut self, frame: &[u8]) -> Result<bool, FountainError> {
+        if !is_frame(frame) {
+            return Err(FountainError::NotAFrame);
+        }
+        if frame.len() < HEADER_LEN {
+            return Err(FountainError::Corrupt);
+        }
+        let number = |range: std::ops::Range<usize>| frame[range].iter().fold(0, |n, &byte| n << 8 | byte as usize);
+        let stream = Stream { checksum: number(3..7) as u32, size: number(7..11), block_size: number(11..13) };
+        let (seed, degree) = (number(13..17) as u32, number(17..19));
+        let blocks = block_count(stream.size, stream.block_size.max(1));
+        if stream.block_size == 0 || frame.len() != HEADER_LEN + stream.block_size || degree == 0 || degree > blocks {
+            return Err(FountainError::Corrupt);
+        }
+        match self.stream {
+            Some(current) if current != stream => return Err(FountainError::OtherStream),
+            Some(_) => {},
+            None => {
+                self.stream = Some(stream);
+                self.blocks = vec![None; blocks];
+            },
+        }
+        if self.seen.contains(&seed) {
+            return Ok(false);
+        }
+        self.seen.push(seed);
+
+        let indices = frame_blocks(stream.checksum, seed, degree, blocks);
+        self.pending.push((indices, frame[HEADER_LEN..].to_vec()));
+        self.peel();
+        Ok(true)
+    }
+
+    // XORs known blocks out of pending frames until none is left with
+    // exactly one unknown block
+    fn peel(&mut self) {
+        loop {
+            let mut progress = false;
+            let mut i = 0;
+            while i < self.pending.len() {
+                let (indices, data) = &mut self.pending[i];
+                indices.retain(|&index| match &self.blocks[index] {
+                    Some(block) => {
+                        xor(data, block);
+                        false
+                    },
+                    None => true,
+                });
+                match indices.len() {
+                    0 => {
+                        self.pending.swap_remove(i);
+                    },
+                    1 => {
+                        let (indices, data) = self.pending.swap_remove(i);
+                        self.blocks[indices[0]] = Some(data);
+                        progress = true;
+                    },
+                    _ => i += 1,
+                }
+            }
+            if !progress {
+                break;
+            }
+        }
+    }
+
+    /// Blocks recovered so far and the blocks the file needs.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.blocks.iter().filter(|block| block.is_some()).count(), self.blocks.len())
+    }
+
+    /// Frames added so far, duplicates aside.
+    pub fn frames(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.stream.is_some() && self.blocks.iter().all(Option::is_some)
+    }
+
+    /// The file, once every block has been recovered.
+    pub fn finish(&self) -> Option<Result<Vec<u8>, FountainError>> {
+        let stream = self.stream.filter(|_| self.is_complete())?;
+        let mut data: Vec<u8> = self.blocks.iter().flatten().flatten().copied().collect();
+        data.truncate(stream.size);
+        Some(if checksum(&data) == stream.checksum { Ok(data) } else { Err(FountainError::ChecksumMismatch) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(size: usize) -> Vec<u8> {
+        (0..size).map(|i| (i * 7 + i / 256) as u8).collect()
+    }
+
+    // `frames` in an order that only depends on `seed`
+    fn shuffled(mut frames: Vec<Vec<u8>>, seed: u32) -> Vec<Vec<u8>> {
+        let mut rng = Rng::new(0, seed);
+        for i in (1..frames.len()).rev() {
+            frames.swap(i, rng.next() as usize % (i + 1));
+        }
+        frames
+    }
+
+    #[test]
+    fn checksum_is_adler32() {
+        assert_eq!(checksum(b"Wikipedia"), 0x11e6_0398);
+        assert_eq!(checksum(b""), 1);
+    }
+
+    #[test]
+    fn recovers_from_a_shuffled_subset() {
+        let data = file(5000);
+        let blocks = block_count(data.len(), 100);
+        let frames = encode(&data, 100, frame_count(data.len(), 100, 200));
+        // a third of the frames that carry one block on their own are missed
+        let kept: Vec<Vec<u8>> = frames.into_iter().enumerate()
+            .filter(|(index, _)| *index >= blocks || index % 3 != 0)
+            .map(|(_, frame)| frame)
+            .collect();
+
+        let mut decoder = FountainDecoder::new();
+        for frame in shuffled(kept, 7) {
+            assert_eq!(decoder.add_frame(&frame), Ok(true));
+            if decoder.is_complete() {
+                break;
+            }
+        }
+        assert_eq!(decoder.progress(), (blocks, blocks));
+        assert_eq!(decoder.finish(), Some(Ok(data)));
+    }
+
+    #[test]
+    fn recovers_from_mixed_frames_alone() {
+        let data = file(1234);
+        let blocks = block_count(data.len(), 64);
+        let frames = encode(&data, 64, blocks * 4);
+        let mut decoder = FountainDecoder::new();
+        for frame in shuffled(frames[blocks..].to_vec(), 3) {
+            decoder.add_frame(&frame).unwrap();
+        }
+        assert_eq!(decoder.finish(), Some(Ok(data)));
+    }
+
+    #[test]
+    fn rejects_repeated_foreign_and_damaged_frames() {
+        let frames = encode(&file(300), 100, 4);
+        let mut decoder = FountainDecoder::new();
+        assert_eq!(decoder.add_frame(&frames[0]), Ok(true));
+        assert_eq!(decoder.add_frame(&frames[0]), Ok(false));
+        assert_eq!(decoder.add_frame(&encode(b"another file", 100, 1)[0]), Err(FountainError::OtherStream));
+        assert_eq!(decoder.add_frame(&frames[1][..HEADER_LEN + 10]), Err(FountainError::Corrupt));
+        assert_eq!(decoder.add_frame(b"not a frame"), Err(FountainError::NotAFrame));
+        assert_eq!(decoder.finish(), None);
+    }
+}
@@ -0,0 +1,287 @@
+//! GS1 payloads for product packaging: a GTIN with optional batch, expiry
+//! date and serial number, written either as FNC1 element strings (GS1 QR) or
+//! as a GS1 Digital Link URI.
+
+use std::fmt;
+
+/// The resolver GS1 runs itself, used when no other domain is given.
+pub const DEFAULT_RESOLVER: &str = "https://id.gs1.org";
+
+// stands in for FNC1 between a variable length element and the next one
+const GROUP_SEPARATOR: u8 = 0x1d;
+
+/// Application Identifiers the editor supports. Empty strings leave an
+/// element out; only the GTIN is required.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Gs1Data {
+    /// AI (01), 8, 12, 13 or 14 digits including the check digit.
+    pub gtin: String,
+    /// AI (10), batch or lot number.
+    pub batch: String,
+    /// AI (17), expiration date as YYMMDD. A day of 00 means the end of the month.
+    pub expiry: String,
+    /// AI (21), serial number.
+    pub serial: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Gs1Format {
+    /// GS1 QR: element strings flagged with FNC1 in first position.
+    ElementString,
+    /// A GS1 Digital Link URI on the given resolver, e.g. `https://id.gs1.org`.
+    DigitalLink { resolver: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Gs1Error {
+    MissingGtin,
+    InvalidGtin,
+    WrongCheckDigit { expected: u8, found: u8 },
+    InvalidExpiry,
+    InvalidCharacter { ai: &'static str, character: char },
+    TooLong { ai: &'static str, max: usize },
+    /// The human readable input names an AI the editor does not handle.
+    UnsupportedAi(String),
+    /// The human readable input is not a list of `(AI)value` pairs.
+    Malformed,
+}
+
+impl fmt::Display for Gs1Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Gs1Error::MissingGtin => write!(f, "A GS1 code needs a GTIN."),
+            Gs1Error::InvalidGtin => write!(f, "A GTIN is 8, 12, 13 or 14 digits long."),
+            Gs1Error::WrongCheckDigit { expected, found } => {
+                write!(f, "The GTIN check digit is {found} but should be {expected}.")
+            },
+            Gs1Error::InvalidExpiry => write!(f, "The expiry date (17) must be a valid date written as YYMMDD."),
+            Gs1Error::InvalidCharacter { ai, character } => {
+                write!(f, "'{character}' is not allowed in ({ai}). GS1 only allows letters, digits and !\"%&'()*+,-./:;<=>?_")
+            },
+            Gs1Error::TooLong { ai, max } => write!(f, "({ai}) holds at most {max} characters."),
+            Gs1Error::UnsupportedAi(ai) => {
+                write!(f, "Application Identifier ({ai}) is not supported. Use (01), (10), (17) and (21).")
+            },
+            Gs1Error::Malformed => write!(f, "Write GS1 data as (AI)value pairs, e.g. (01)09506000134352(10)ABC123."),
+        }
+    }
+}
+
+impl std::error::Error for Gs1Error {}
+
+/// The GS1 mod 10 check digit for the digits before it.
+pub fn check_digit(digits: &str) -> u8 {
+    let sum: u32 = digits.bytes()
+        .rev()
+        .enumerate()
+        .map(|(i, digit)| u32::from(digit - b'0') * if i % 2 == 0 { 3 } else { 1 })
+        .sum();
+    ((10 - sum % 10) % 10) as u8
+}
+
+// GS1 AI encodable character set 82
+fn is_gs1_character(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!\"%&'()*+,-./:;<=>?_".contains(c)
+}
+
+fn validate_text(ai: &'static str, text: &str) -> Result<(), Gs1Error> {
+    if let Some(character) = text.chars().find(|&c| !is_gs1_character(c)) {
+        return Err(Gs1Error::InvalidCharacter { ai, character });
+    }
+    if text.len() > 20 {
+        return Err(Gs1Error::TooLong { ai, max: 20 });
+    }
+    Ok(())
+}
+
+fn validate_expiry(expiry: &str) -> Result<(), Gs1Error> {
+    if expiry.len() != 6 || !expiry.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(Gs1Error::InvalidExpiry);
+    }
+    let number = |range: std::ops::Range<usize>| expiry[range].parse::<u8>().expect("checked digits");
+    let (year, month, day) = (number(0..2), number(2..4), number(4..6));
+    // every year a two digit YY can stand for that is divisible by 4 is a leap year
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if year % 4 == 0 => 29,
+        2 => 28,
+        _ => return Err(Gs1Error::InvalidExpiry),
+    };
+    if day <= days { Ok(()) } else { Err(Gs1Error::InvalidExpiry) }
+}
+
+// RFC 3986 unreserved characters stay, everything else is escaped
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => char::from(b).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+// where the next `(AI)` starts in `text`; a `(` in a value, which GS1
+// allows, is not followed by digits and `)`
+fn next_ai(text: &str) -> Option<usize> {
+    text.match_indices('(').map(|(index, _)| index).find(|&index| {
+        text[index + 1..].split_once(')')
+            .is_some_and(|(ai, _)| (2..=4).contains(&ai.len()) && ai.bytes().all(|b| b.is_ascii_digit()))
+    })
+}
+
+impl Gs1Data {
+    /// Reads human readable element strings such as
+    /// `(01)09506000134352(17)251231(10)ABC123`.
+    pub fn parse(hri: &str) -> Result<Gs1Data, Gs1Error> {
+        let mut data = Gs1Data::default();
+        let mut rest = hri.trim();
+        while !rest.is_empty() {
+            let (ai, after) = rest.strip_prefix('(')
+                .and_then(|rest| rest.split_once(')'))
+                .ok_or(Gs1Error::Malformed)?;
+            let (value, next) = after.split_at(next_ai(after).unwrap_or(after.len()));
+            let field = match ai {
+                "01" => &mut data.gtin,
+                "10" => &mut data.batch,
+                "17" => &mut data.expiry,
+                "21" => &mut data.serial,
+                _ => return Err(Gs1Error::UnsupportedAi(ai.to_string())),
+            };
+            *field = value.to_string();
+            rest = next;
+        }
+        Ok(data)
+    }
+
+    /// The GTIN padded to 14 digits, after checking its length and check digit.
+    pub fn gtin14(&self) -> Result<String, Gs1Error> {
+        let gtin = self.gtin.trim();
+        if gtin.is_empty() {
+            return Err(Gs1Error::MissingGtin);
+        }
+        if !matches!(gtin.len(), 8 | 12 | 13 | 14) || !gtin.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Gs1Error::InvalidGtin);
+        }
+
+        let (body, last) = gtin.split_at(gtin.len() - 1);
+        let (expected, found) = (check_digit(body), last.as_bytes()[0] - b'0');
+        if expected != found {
+            return Err(Gs1Error::WrongCheckDigit { expected, found });
+        }
+        Ok(format!("{gtin:0>14}"))
+    }
+
+    pub fn validate(&self) -> Result<(), Gs1Error> {
+        self.gtin14()?;
+        if !self.expiry.is_empty() {
+            validate_expiry(&self.expiry)?;
+        }
+        validate_text("10", &self.batch)?;
+        validate_text("21", &self.serial)
+    }
+
+    /// The element strings with fixed length AIs first, so only the batch
+    /// needs a separator after it when a serial follows.
+    pub fn element_string(&self) -> Result<Vec<u8>, Gs1Error> {
+        self.validate()?;
+        let mut bytes = format!("01{}", self.gtin14()?).into_bytes();
+        if !self.expiry.is_empty() {
+            bytes.extend_from_slice(format!("17{}", self.expiry).as_bytes());
+        }
+        if !self.batch.is_empty() {
+            bytes.extend_from_slice(format!("10{}", self.batch).as_bytes());
+        }
+        if !self.serial.is_empty() {
+            if !self.batch.is_empty() {
+                bytes.push(GROUP_SEPARATOR);
+            }
+            bytes.extend_from_slice(format!("21{}", self.serial).as_bytes());
+        }
+        Ok(bytes)
+    }
+
+    /// A Digital Link URI, with the batch and serial as key qualifiers in the
+    /// path and the expiry as a query attribute.
+    pub fn digital_link(&self, resolver: &str) -> Result<String, Gs1Error> {
+        self.validate()?;
+        let mut uri = format!("{}/01/{}", resolver.trim_end_matches('/'), self.gtin14()?);
+        if !self.batch.is_empty() {
+            uri.push_str(&format!("/10/{}", percent_encode(&self.batch)));
+        }
+        if !self.serial.is_empty() {
+            uri.push_str(&format!("/21/{}", percent_encode(&self.serial)));
+        }
+        if !self.expiry.is_empty() {
+            uri.push_str(&format!("?17={}", self.expiry));
+        }
+        Ok(uri)
+    }
+
+    /// The bytes to encode in `format`, and whether they are element strings
+    /// that need FNC1.
+    pub fn payload(&self, format: &Gs1Format) -> Result<(Vec<u8>, bool), Gs1Error> {
+        match format {
+            Gs1Format::ElementString => Ok((self.element_string()?, true)),
+            Gs1Format::DigitalLink { resolver } => Ok((self.digital_link(resolver)?.into_bytes(), false)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expiry_days_follow_the_month() {
+        assert!(validate_expiry("240229").is_ok());
+        assert!(validate_expiry("240400").is_ok());
+        assert!(validate_expiry("241231").is_ok());
+        assert_eq!(validate_expiry("230229"), Err(Gs1Error::InvalidExpiry));
+        assert_eq!(validate_expiry("240231"), Err(Gs1Error::InvalidExpiry));
+        assert_eq!(validate_expiry("240431"), Err(Gs1Error::InvalidExpiry));
+        assert_eq!(validate_expiry("241301"), Err(Gs1Error::InvalidExpiry));
+    }
+
+    #[test]
+    fn parse_keeps_parentheses_in_values() {
+        let data = Gs1Data::parse("(01)09506000134352(10)A(B)C(21)(1)").unwrap();
+        assert_eq!(data.batch, "A(B)C");
+        assert_eq!(data.serial, "(1)");
+        assert_eq!(Gs1Data::parse("(99)1"), Err(Gs1Error::UnsupportedAi(String::from("99"))));
+    }
+
+    #[test]
+    fn element_string_separates_variable_length_elements() {
+        let data = Gs1Data::parse("(01)9506000134352(17)251231(10)ABC(21)12").unwrap();
+        assert_eq!(data.element_string().unwrap(), b"01095060001343521725123110ABC\x1d2112");
+    }
+
+    #[test]
+    fn percent_survives_alphanumeric_mode() {
+        use crate::core::{decode, QrOptions, QrRequest, DEFAULT_MODULE_SIZE};
+
+        let data = Gs1Data::parse("(01)09506000134352(10)ABCDEFGHIJ%KLMNOPQR").unwrap();
+        let code = QrRequest::from_gs1(&data, &Gs1Format::ElementString, QrOptions::default()).unwrap().generate().unwrap();
+        assert!(code.segments().iter().any(|segment| segment.mode == qrcode::types::Mode::Alphanumeric));
+        let image = image::DynamicImage::ImageRgb8(code.to_image(DEFAULT_MODULE_SIZE)).to_luma8();
+        assert_eq!(decode::decode(&image).unwrap().payload, data.element_string().unwrap());
+    }
+
+    #[test]
+    fn separator_stays_in_the_alphanumeric_segment() {
+        use crate::core::{decode, QrOptions, QrRequest, DEFAULT_MODULE_SIZE};
+        use crate::core::segment::{Mode, Segment};
+
+        let data = Gs1Data::parse("(01)09506000134352(10)ABC123(21)42").unwrap();
+        let payload = data.element_string().unwrap();
+        let code = QrRequest::from_gs1(&data, &Gs1Format::ElementString, QrOptions::default()).unwrap().generate().unwrap();
+        let alphanumeric = payload.iter().position(|&byte| byte == b'A').unwrap();
+        assert_eq!(code.segments(), [
+            Segment { mode: Mode::Numeric, begin: 0, end: alphanumeric },
+            Segment { mode: Mode::Alphanumeric, begin: alphanumeric, end: payload.len() },
+        ]);
+        let image = image::DynamicImage::ImageRgb8(code.to_image(DEFAULT_MODULE_SIZE)).to_luma8();
+        assert_eq!(decode::decode(&image).unwrap().payload, payload);
+    }
+}
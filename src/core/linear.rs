@@ -0,0 +1,312 @@
+//! Linear barcodes: Code 128 (ISO/IEC 15417), with GS1-128 for GS1 data, and
+//! EAN-13 and UPC-A (ISO/IEC 15420) with their check digits.
+
+use super::{GenerateError, Symbology};
+
+/// Bar height of a linear barcode, in modules.
+pub const BAR_HEIGHT: usize = 50;
+
+// bar and space widths of every Code 128 symbol character, by value
+const CODE128_PATTERNS: [&[u8; 6]; 106] = [
+    b"212222", b"222122", b"222221", b"121223", b"121322", b"131222", b"122213", b"122312", b"132212", b"221213",
+    b"221312", b"231212", b"112232", b"122132", b"122231", b"113222", b"123122", b"123221", b"223211", b"221132",
+    b"221231", b"213212", b"223112", b"312131", b"311222", b"321122", b"321221", b"312212", b"322112", b"322211",
+    b"212123", b"212321", b"232121", b"111323", b"131123", b"131321", b"112313", b"132113", b"132311", b"211313",
+    b"231113", b"231311", b"112133", b"112331", b"132131", b"113123", b"113321", b"133121", b"313121", b"211331",
+    b"231131", b"213113", b"213311", b"213131", b"311123", b"311321", b"331121", b"312113", b"312311", b"332111",
+    b"314111", b"221411", b"431111", b"111224", b"111422", b"121124", b"121421", b"141122", b"141221", b"112214",
+    b"112412", b"122114", b"122411", b"142112", b"142211", b"241211", b"221114", b"413111", b"241112", b"134111",
+    b"111242", b"121142", b"121241", b"114212", b"124112", b"124211", b"411212", b"421112", b"421211", b"212141",
+    b"214121", b"412121", b"111143", b"111341", b"131141", b"114113", b"114311", b"411113", b"411311", b"113141",
+    b"114131", b"311141", b"411131", b"211412", b"211214", b"211232",
+];
+const CODE128_STOP: &[u8] = b"2331112";
+
+const CODE_C: u8 = 99;
+const CODE_B: u8 = 100;
+const CODE_A: u8 = 101;
+const FNC1: u8 = 102;
+const START_A: u8 = 103;
+const START_B: u8 = 104;
+const START_C: u8 = 105;
+// a digit run at least this long is worth switching to code set C for
+const MIN_DIGIT_RUN: usize = 4;
+
+// space and bar widths of the left-hand odd parity (L) digits
+const EAN_DIGITS: [&[u8; 4]; 10] = [b"3211", b"2221", b"2122", b"1411", b"1132", b"1231", b"1114", b"1312", b"1213", b"3112"];
+// which of the six left-hand digits use even parity (G), by the leading digit
+const EAN_PARITY: [u8; 10] = [0b000000, 0b001011, 0b001101, 0b001110, 0b010011, 0b011001, 0b011100, 0b010101, 0b010110, 0b011010];
+
+/// An encoded linear barcode: one module per entry, left to right.
+pub(super) struct Linear {
+    pub bars: Vec<bool>,
+    /// The human-readable interpretation of the data.
+    pub text: String,
+}
+
+// appends alternating bars and spaces of the given widths, starting with a bar
+fn push_widths(bars: &mut Vec<bool>, widths: &[u8], starts_dark: bool) {
+    for (i, &width) in widths.iter().enumerate() {
+        let dark = (i % 2 == 0) == starts_dark;
+        bars.extend(std::iter::repeat_n(dark, (width - b'0') as usize));
+    }
+}
+
+pub(super) fn encode(symbology: Symbology, data: &[u8], gs1: bool) -> Result<Linear, GenerateError> {
+    match symbology {
+        Symbology::Code128 => encode_code128(data, gs1),
+        Symbology::Ean13 => encode_ean13(symbology, data, 12),
+        Symbology::UpcA => encode_ean13(symbology, data, 11),
+        _ => unreachable!("{symbology} is not linear"),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CodeSet {
+    A,
+    B,
+    C,
+}
+
+impl CodeSet {
+    // the value of a byte, if this code set has it
+    fn value(self, byte: u8, gs1: bool) -> Option<u8> {
+        match (self, byte) {
+            (_, 0x1d) if gs1 => Some(FNC1),
+            (CodeSet::A, 0..=31) => Some(byte + 64),
+            (CodeSet::A, 32..=95) | (CodeSet::B, 32..=127) => Some(byte - 32),
+            _ => None,
+        }
+    }
+}
+
+fn digit_run(data: &[u8]) -> usize {
+    data.iter().take_while(|byte| byte.is_ascii_digit()).count()
+}
+
+// code set C for digit runs, otherwise B unless a control character comes
+// before the next lowercase letter
+fn code_set_for(data: &[u8], gs1: bool) -> CodeSet {
+    let run = digit_run(data);
+    if run >= MIN_DIGIT_RUN || (run == data.len() && run.is_multiple_of(2) && run > 0) {
+        return CodeSet::C;
+    }
+    match data.iter().find(|&&byte| !((32..=95).contains(&byte) || gs1 && byte == 0x1d)) {
+        Some(&byte) if byte < 32 => CodeSet::A,
+        _ => CodeSet::B,
+    }
+}
+
+fn encode_code128(data: &[u8], gs1: bool) -> Result<Linear, GenerateError> {
+    if let Some(&byte) = data.iter().find(|byte| !byte.is_ascii()) {
+        return Err(GenerateError::UnsupportedByte { symbology: Symbology::Code128, byte });
+    }
+
+    let mut set = code_set_for(data, gs1);
+    let mut values = vec![match set {
+        CodeSet::A => START_A,
+        CodeSet::B => START_B,
+        CodeSet::C => START_C,
+    }];
+    if gs1 {
+        values.push(FNC1);
+    }
+
+    let mut i = 0;
+    while i < data.len() {
+        let rest = &data[i..];
+        if set == CodeSet::C {
+            if gs1 && rest[0] == 0x1d {
+                values.push(FNC1);
+                i += 1;
+                continue;
+            }
+            if digit_run(rest) >= 2 {
+                values.push((rest[0] - b'0') * 10 + rest[1] - b'0');
+                i += 2;
+                continue;
+            }
+        } else {
+            let run = digit_run(rest);
+            // an odd run goes to code set C after its first digit
+            if run >= MIN_DIGIT_RUN && run.is_multiple_of(2) {
+                values.push(CODE_C);
+                set = CodeSet::C;
+                continue;
+            }
+            if let Some(value) = set.value(rest[0], gs1) {
+                values.push(value);
+                i += 1;
+                continue;
+            }
+        }
+
+        set = match code_set_for(rest, gs1) {
+            CodeSet::C => CodeSet::B,
+            next => next,
+        };
+        values.push(if set == CodeSet::A { CODE_A } else { CODE_B });
+    }
+
+    let checksum = values.iter().enumerate().map(|(i, &value)| i.max(1) * value as usize).sum::<usize>() % 103;
+    values.push(checksum as u8);
+
+    let mut bars = Vec::new();
+    for value in values {
+        push_widths(&mut bars, CODE128_PATTERNS[value as usize], true);
+    }
+    push_widths(&mut bars, CODE128_STOP, true);
+
+    let text = data.iter().map(|&byte| if byte.is_ascii_graphic() { byte as char } else { ' ' }).collect();
+    Ok(Linear { bars, text })
+}
+
+// the EAN/UPC check digit of `digits`, which must all be ASCII digits
+fn check_digit(digits: &[u8]) -> u8 {
+    // weights alternate 3 and 1 from the rightmost digit
+    let sum: u32 = digits.iter().rev().enumerate().map(|(i, digit)| (digit - b'0') as u32 * if i % 2 == 0 { 3 } else { 1 }).sum();
+    ((10 - sum % 10) % 10) as u8
+}
+
+// UPC-A is an EAN-13 with a leading 0, so `length` is the data digits without
+// the check digit: 12 for EAN-13, 11 for UPC-A
+fn encode_ean13(symbology: Symbology, data: &[u8], length: usize) -> Result<Linear, GenerateError> {
+    if !data.iter().all(u8::is_ascii_digit) || !(length..=length + 1).contains(&data.len()) {
+        return Err(GenerateError::InvalidLength { symbology, digits: length });
+    }
+    let check = check_digit(&data[..length]);
+    if let Some(&given) = data.get(length) {
+        if given - b'0' != check {
+            return Err(GenerateError::InvalidCheckDigit { symbology, given: given - b'0', expected: check });
+        }
+    }
+
+    let mut digits: Vec<u8> = data[..length].iter().map(|digit| digit - b'0').collect();
+    digits.push(check);
+    let text = digits.iter().map(|digit| char::from(b'0' + digit)).collect();
+    if length == 11 {
+        digits.insert(0, 0);
+    }
+
+    let mut bars = Vec::new();
+    push_widths(&mut bars, b"111", true);
+    let parity = EAN_PARITY[digits[0] as usize];
+    for (i, &digit) in digits[1..7].iter().enumerate() {
+        let widths = EAN_DIGITS[digit as usize];
+        if parity >> (5 - i) & 1 == 1 {
+            let mut reversed = *widths;
+            reversed.reverse();
+            push_widths(&mut bars, &reversed, false);
+        } else {
+            push_widths(&mut bars, widths, false);
+        }
+    }
+    push_widths(&mut bars, b"11111", false);
+    for &digit in &digits[7..] {
+        push_widths(&mut bars, EAN_DIGITS[digit as usize], true);
+    }
+    push_widths(&mut bars, b"111", true);
+    Ok(Linear { bars, text })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the widths of the runs of bars and spaces, as ASCII digits
+    fn widths(bars: &[bool]) -> Vec<u8> {
+        let mut widths = Vec::new();
+        for (i, &dark) in bars.iter().enumerate() {
+            if i > 0 && bars[i - 1] == dark {
+                *widths.last_mut().unwrap() += 1;
+            } else {
+                widths.push(b'1');
+            }
+        }
+        widths
+    }
+
+    // the symbol character values of a Code 128 symbol, check character
+    // included, after checking the stop pattern
+    fn code128_values(data: &[u8], gs1: bool) -> Vec<u8> {
+        let widths = widths(&encode(Symbology::Code128, data, gs1).unwrap().bars);
+        let (characters, stop) = widths.split_at(widths.len() - CODE128_STOP.len());
+        assert_eq!(stop, CODE128_STOP);
+        characters.chunks(6).map(|pattern| CODE128_PATTERNS.iter().position(|&known| known == pattern).unwrap() as u8).collect()
+    }
+
+    // the 13 digits an EAN-13 symbol shows, the first one read from the
+    // parity of the left half
+    fn ean13_digits(symbology: Symbology, data: &[u8]) -> Vec<u8> {
+        let widths = widths(&encode(symbology, data, false).unwrap().bars);
+        assert_eq!(widths.len(), 3 + 6 * 4 + 5 + 6 * 4 + 3);
+        assert_eq!((&widths[..3], &widths[27..32], &widths[56..]), (&b"111"[..], &b"11111"[..], &b"111"[..]));
+        let digit = |pattern: &[u8]| EAN_DIGITS.iter().position(|&known| known == pattern);
+
+        let mut parity = 0;
+        let mut digits = vec![0];
+        for pattern in widths[3..27].chunks(4) {
+            let mut reversed = pattern.to_vec();
+            reversed.reverse();
+            let (value, even) = digit(pattern).map(|value| (value, 0)).or_else(|| digit(&reversed).map(|value| (value, 1))).unwrap();
+            parity = parity << 1 | even;
+            digits.push(value as u8);
+        }
+        digits[0] = EAN_PARITY.iter().position(|&known| known == parity).unwrap() as u8;
+        digits.extend(widths[32..56].chunks(4).map(|pattern| digit(pattern).unwrap() as u8));
+        digits
+    }
+
+    fn digits(text: &str) -> Vec<u8> {
+        text.bytes().map(|digit| digit - b'0').collect()
+    }
+
+    #[test]
+    fn ean13_adds_the_check_digit() {
+        assert_eq!(encode(Symbology::Ean13, b"400638133393", false).unwrap().text, "4006381333931");
+        assert_eq!(ean13_digits(Symbology::Ean13, b"400638133393"), digits("4006381333931"));
+        assert_eq!(ean13_digits(Symbology::Ean13, b"5901234123457"), digits("5901234123457"));
+        assert_eq!(
+            encode(Symbology::Ean13, b"5901234123450", false).err(),
+            Some(GenerateError::InvalidCheckDigit { symbology: Symbology::Ean13, given: 0, expected: 7 }),
+        );
+        assert_eq!(
+            encode(Symbology::Ean13, b"59012341234", false).err(),
+            Some(GenerateError::InvalidLength { symbology: Symbology::Ean13, digits: 12 }),
+        );
+    }
+
+    #[test]
+    fn upc_a_is_an_ean13_with_a_leading_zero() {
+        assert_eq!(encode(Symbology::UpcA, b"03600029145", false).unwrap().text, "036000291452");
+        assert_eq!(ean13_digits(Symbology::UpcA, b"03600029145"), digits("0036000291452"));
+        assert_eq!(ean13_digits(Symbology::UpcA, b"012345678905"), digits("0012345678905"));
+        assert_eq!(
+            encode(Symbology::UpcA, b"012345678901", false).err(),
+            Some(GenerateError::InvalidCheckDigit { symbology: Symbology::UpcA, given: 1, expected: 5 }),
+        );
+    }
+
+    #[test]
+    fn code128_switches_code_sets() {
+        // W i k i p e d i a in code set B, then the check character
+        assert_eq!(code128_values(b"Wikipedia", false), [START_B, 55, 73, 75, 73, 80, 69, 68, 73, 65, 88]);
+        // an even run of digits in code set C
+        assert_eq!(code128_values(b"123456", false), [START_C, 12, 34, 56, 44]);
+        // a control character needs code set A
+        assert_eq!(code128_values(b"A\tB", false), [START_A, 33, 73, 34, 75]);
+        assert_eq!(
+            encode(Symbology::Code128, "é".as_bytes(), false).err(),
+            Some(GenerateError::UnsupportedByte { symbology: Symbology::Code128, byte: 0xc3 }),
+        );
+    }
+
+    #[test]
+    fn gs1_128_starts_with_fnc1_and_separates_with_it() {
+        assert_eq!(code128_values(b"0109501101530003", true), [START_C, FNC1, 1, 9, 50, 11, 1, 53, 0, 3, 71]);
+        assert_eq!(
+            code128_values(b"010950110153000310AB-123\x1d2112", true),
+            [START_C, FNC1, 1, 9, 50, 11, 1, 53, 0, 3, 10, CODE_B, 33, 34, 13, 17, 18, 19, FNC1, CODE_C, 21, 12, 27],
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! Mask selection. `qrcode` only exposes the best mask, so the penalty rules
+//! are reimplemented here to let users see and override the choice. They
+//! follow `qrcode`'s scoring exactly, so "automatic" picks the same mask.
+
+use qrcode::canvas::MaskPattern;
+use qrcode::types::{Color as Module, Version};
+
+/// Number of mask patterns available to normal QR codes.
+pub const MASK_COUNT: u8 = 8;
+
+/// The pattern a mask reference number (as printed in the format information)
+/// stands for in the given version.
+pub fn mask_pattern(version: Version, mask: u8) -> Option<MaskPattern> {
+    let pattern = match (version, mask) {
+        (Version::Normal(_), 0) => MaskPattern::Checkerboard,
+        (Version::Normal(_), 1) => MaskPattern::HorizontalLines,
+        (Version::Normal(_), 2) => MaskPattern::VerticalLines,
+        (Version::Normal(_), 3) => MaskPattern::DiagonalLines,
+        (Version::Normal(_), 4) => MaskPattern::LargeCheckerboard,
+        (Version::Normal(_), 5) => MaskPattern::Fields,
+        (Version::Normal(_), 6) => MaskPattern::Diamonds,
+        (Version::Normal(_), 7) => MaskPattern::Meadow,
+        (Version::Micro(_), 0) => MaskPattern::HorizontalLines,
+        (Version::Micro(_), 1) => MaskPattern::LargeCheckerboard,
+        (Version::Micro(_), 2) => MaskPattern::Diamonds,
+        (Version::Micro(_), 3) => MaskPattern::Meadow,
+        _ => return None,
+    };
+    Some(pattern)
+}
+
+/// How many masks the given version can choose from.
+pub fn mask_count(version: Version) -> u8 {
+    if version.is_micro() { 4 } else { MASK_COUNT }
+}
+
+struct Grid<'a> {
+    modules: &'a [Module],
+    width: usize,
+}
+
+impl Grid<'_> {
+    fn get(&self, x: usize, y: usize) -> Module {
+        self.modules[y * self.width + x]
+    }
+
+    fn line(&self, i: usize, is_horizontal: bool) -> impl Iterator<Item = Module> + '_ {
+        (0..self.width).map(move |j| if is_horizontal { self.get(j, i) } else { self.get(i, j) })
+    }
+
+    // every run of 5+N same-colored modules in a row or column scores 3+N
+    fn adjacent_score(&self, is_horizontal: bool) -> u32 {
+        let mut total_score = 0;
+        for i in 0..self.width {
+            let mut last_color = None;
+            let mut run = 0;
+            for color in self.line(i, is_horizontal).map(Some).chain(Some(None)) {
+                if color == last_color {
+                    run += 1;
+                } else {
+                    if run >= 5 {
+                        total_score += run - 2;
+                    }
+                    last_color = color;
+                    run = 1;
+                }
+            }
+        }
+        total_score
+    }
+
+    // every (overlapping) 2x2 block of one color scores 3
+    fn block_score(&self) -> u32 {
+        let mut total_score = 0;
+        for y in 0..self.width - 1 {
+            for x in 0..self.width - 1 {
+                let this = self.get(x, y);
+                if this == self.get(x + 1, y) && this == self.get(x, y + 1) && this == self.get(x + 1, y + 1) {
+                    total_score += 3;
+                }
+            }
+        }
+        total_score
+    }
+
+    // every `#.###.#` not padded by four light modules on one side scores 40,
+    // not counting the three finder patterns themselves
+    fn finder_score(&self, is_horizontal: bool) -> u32 {
+        const PATTERN: [Module; 7] =
+            [Module::Dark, Module::Light, Module::Dark, Module::Dark, Module::Dark, Module::Light, Module::Dark];
+
+        let mut total_score: u32 = 0;
+        for i in 0..self.width {
+            let line: Vec<Module> = self.line(i, is_horizontal).collect();
+            for j in 0..self.width - 6 {
+                if line[j..j + 7] != PATTERN {
+                    continue;
+                }
+
+                let is_dark = |k: usize| line.get(k) == Some(&Module::Dark);
+                if !(j.saturating_sub(4)..j).any(is_dark) || !(j + 7..j + 11).any(is_dark) {
+                    total_score += 40;
+                }
+            }
+        }
+        total_score.saturating_sub(360)
+    }
+
+    // deviation from an even dark/light split, up to 100
+    fn balance_score(&self) -> u32 {
+        let dark_modules = self.modules.iter().filter(|&&m| m == Module::Dark).count();
+        let ratio = dark_modules * 200 / self.modules.len();
+        ratio.abs_diff(100) as u32
+    }
+
+    // Micro QR only looks at how many light modules sit on the right and bottom edges
+    fn light_side_score(&self) -> u32 {
+        let last = self.width - 1;
+        let h = (1..self.width).filter(|&j| self.get(j, last) == Module::Light).count();
+        let v = (1..self.width).filter(|&j| self.get(last, j) == Module::Light).count();
+        (h + v + 15 * h.max(v)) as u32
+    }
+}
+
+/// The penalty score of a masked symbol. Lower is better.
+pub fn penalty_score(modules: &[Module], width: usize, version: Version) -> u32 {
+    let grid = Grid { modules, width };
+    if version.is_micro() {
+        grid.light_side_score()
+    } else {
+        grid.adjacent_score(true)
+            + grid.adjacent_score(false)
+            + grid.block_score()
+            + grid.finder_score(true)
+            + grid.finder_score(false)
+            + grid.balance_score()
+    }
+}
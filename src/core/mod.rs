@@ -0,0 +1,696 @@
+//! QR code generation without any dependency on Yew or the browser.
+//!
+//! The web frontend and native tools both go through [`QrRequest::generate`],
+//! so a code produced here is exactly the code the site would produce.
+
+mod aztec;
+mod capacity;
+mod charset;
+pub mod compression;
+mod datamatrix;
+pub mod decode;
+mod error;
+pub mod fountain;
+pub mod gs1;
+mod linear;
+mod mask;
+mod pdf417;
+mod reed_solomon;
+mod render;
+mod rmqr;
+pub mod segment;
+pub mod stress;
+mod structured_append;
+mod symbology;
+
+pub use aztec::{AztecFormat, DEFAULT_EC_PERCENT, MAX_EC_PERCENT, MIN_EC_PERCENT};
+pub use capacity::{cheapest_mode, Capacity, KanjiSavings};
+pub use charset::Charset;
+pub use datamatrix::DataMatrixShape;
+pub use error::GenerateError;
+pub use linear::BAR_HEIGHT;
+pub use structured_append::{StructuredAppend, MAX_SYMBOLS};
+pub use symbology::{SymbolSize, Symbology};
+pub use mask::{mask_count, MASK_COUNT};
+pub use pdf417::{MAX_PDF417_COLUMNS, MAX_SECURITY_LEVEL};
+pub use render::{encode_gif, ImageFormat, RenderError, DEFAULT_FRAME_DELAY_MS, DEFAULT_MODULE_SIZE, MAX_QUIET_ZONE};
+pub use rmqr::{RMQR_HEIGHTS, RMQR_WIDTHS};
+pub use qrcode::types::{EcLevel, Version};
+
+use std::borrow::Cow;
+use std::iter;
+
+use crate::color::Color;
+use gs1::{Gs1Data, Gs1Format};
+use segment::{Mode, Segment};
+use qrcode::bits::Bits;
+use qrcode::canvas::Canvas;
+use qrcode::ec::construct_codewords;
+use qrcode::types::{Color as Module, QrError};
+
+pub const MIN_VERSION: i16 = 1;
+pub const MAX_VERSION: i16 = 40;
+
+/// Everything that changes how a payload is turned into a symbol.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QrOptions {
+    pub symbology: Symbology,
+    pub ec_level: EcLevel,
+    /// Ignore `ec_level` and use the highest level that still fits in the
+    /// allowed versions.
+    pub auto_ec_level: bool,
+    /// The smallest version the encoder may pick. Equal to `max_version` to pin a version.
+    pub min_version: i16,
+    /// The largest version the encoder may pick.
+    pub max_version: i16,
+    /// Mask reference number to force, or `None` to pick the lowest penalty.
+    pub mask: Option<u8>,
+    /// Character set to transcode text into and declare with an ECI header,
+    /// or `None` to encode the payload bytes as they are.
+    pub eci: Option<Charset>,
+    /// Transcode text to Shift-JIS (without an ECI header) so kanji can use
+    /// the compact Kanji mode. Text Shift-JIS cannot represent stays UTF-8.
+    pub kanji_mode: bool,
+    /// Split payloads too large for one symbol into a Structured Append
+    /// sequence when generating a set. Off by default, so a pinned or
+    /// limited version range fails instead of quietly making many codes.
+    pub structured_append: bool,
+    pub data_matrix_shape: DataMatrixShape,
+    pub aztec_format: AztecFormat,
+    /// Share of an Aztec symbol spent on error correction, in percent.
+    pub aztec_ec_percent: u8,
+    /// PDF417 data columns, or `None` for a symbol about twice as wide as high.
+    pub pdf417_columns: Option<usize>,
+    /// PDF417 security level, or `None` for the one ISO/IEC 15438
+    /// recommends for the length of the data.
+    pub pdf417_security_level: Option<u8>,
+    /// rMQR height in modules, or `None` for any.
+    pub rmqr_height: Option<usize>,
+    /// rMQR width in modules, or `None` for any.
+    pub rmqr_width: Option<usize>,
+    /// Print the data as text under linear barcodes.
+    pub human_readable: bool,
+    /// Modules of blank margin around the symbol, or `None` for what the
+    /// symbology asks for. Narrower ones save space but scan less reliably.
+    pub quiet_zone: Option<usize>,
+    pub foreground_color: Color,
+    pub background_color: Color,
+}
+
+impl QrOptions {
+    /// Whether the payload is Shift-JIS, so Kanji segments decode correctly.
+    pub fn allows_kanji(&self) -> bool {
+        match self.eci {
+            Some(charset) => charset == Charset::ShiftJis,
+            None => self.kanji_mode,
+        }
+    }
+}
+
+impl Default for QrOptions {
+    fn default() -> Self {
+        QrOptions {
+            symbology: Symbology::Qr,
+            ec_level: EcLevel::M,
+            auto_ec_level: false,
+            min_version: MIN_VERSION,
+            max_version: MAX_VERSION,
+            mask: None,
+            eci: None,
+            kanji_mode: false,
+            structured_append: false,
+            data_matrix_shape: DataMatrixShape::Square,
+            aztec_format: AztecFormat::Auto,
+            aztec_ec_percent: DEFAULT_EC_PERCENT,
+            pdf417_columns: None,
+            pdf417_security_level: None,
+            rmqr_height: None,
+            rmqr_width: None,
+            human_readable: true,
+            quiet_zone: None,
+            foreground_color: Color::from_rgb(0.0, 0.0, 0.0),
+            background_color: Color::from_rgb(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+/// A payload together with the options to encode it with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QrRequest {
+    pub data: Vec<u8>,
+    pub options: QrOptions,
+    /// Manual segmentation of the encoded payload (`data` after any ECI
+    /// transcoding), or `None` to let the optimizer decide.
+    pub segments: Option<Vec<Segment>>,
+    /// `data` is GS1 element strings, flagged with FNC1 in first position.
+    pub gs1: bool,
+}
+
+impl QrRequest {
+    pub fn new(data: impl Into<Vec<u8>>, options: QrOptions) -> QrRequest {
+        QrRequest { data: data.into(), options, segments: None, gs1: false }
+    }
+
+    /// A request for GS1 data, either as GS1 QR or as a Digital Link URI.
+    pub fn from_gs1(data: &Gs1Data, format: &Gs1Format, options: QrOptions) -> Result<QrRequest, GenerateError> {
+        let (payload, gs1) = data.payload(format)?;
+        Ok(QrRequest { gs1, ..QrRequest::new(payload, options) })
+    }
+
+    pub fn with_segments(self, segments: Option<Vec<Segment>>) -> QrRequest {
+        QrRequest { segments, ..self }
+    }
+
+    /// The bytes that actually get encoded: `data` itself, or `data` read as
+    /// UTF-8 text and transcoded into the ECI character set, or into
+    /// Shift-JIS for Kanji mode.
+    pub fn payload(&self) -> Result<Cow<'_, [u8]>, GenerateError> {
+        let Some(charset) = self.options.eci else {
+            return Ok(self.kanji_payload().map_or(Cow::Borrowed(&self.data), Cow::Owned));
+        };
+        let symbology = self.options.symbology;
+        if symbology == Symbology::MicroQr || symbology.is_linear() {
+            return Err(GenerateError::EciNotSupported(symbology));
+        }
+
+        let text = std::str::from_utf8(&self.data).map_err(|_| GenerateError::EciNotText)?;
+        charset.encode(text)
+            .map(Cow::Owned)
+            .map_err(|character| GenerateError::UnencodableCharacter { charset, character })
+    }
+
+    fn kanji_payload(&self) -> Option<Vec<u8>> {
+        if !self.options.kanji_mode || !self.options.symbology.is_qr_family() {
+            return None;
+        }
+        std::str::from_utf8(&self.data).ok().and_then(|text| Charset::ShiftJis.encode(text).ok())
+    }
+
+    // the versions allowed by the options that offer the requested EC level
+    fn versions(&self) -> Result<Vec<Version>, GenerateError> {
+        let QrOptions { symbology, ec_level, min_version, max_version, .. } = self.options;
+        if !(symbology.min_version()..=max_version).contains(&min_version) || max_version > symbology.max_version() {
+            return Err(GenerateError::InvalidVersionRange { symbology, min_version, max_version });
+        }
+        if self.gs1 && symbology == Symbology::MicroQr {
+            return Err(GenerateError::Gs1NotSupported(symbology));
+        }
+
+        let versions: Vec<Version> = (min_version..=max_version)
+            .map(|number| symbology.version(number))
+            .filter(|&version| Bits::new(version).max_len(ec_level).is_ok())
+            .collect();
+        if versions.is_empty() {
+            return Err(GenerateError::UnsupportedEcLevel { symbology, ec_level, min_version, max_version });
+        }
+        Ok(versions)
+    }
+
+    fn at_ec_level(&self, ec_level: EcLevel) -> QrRequest {
+        QrRequest { options: QrOptions { ec_level, auto_ec_level: false, ..self.options }, ..self.clone() }
+    }
+
+    // tries every level from the highest down, returning the lowest level's
+    // error if none of them fit
+    fn maximize_ec_level<T>(
+        &self,
+        generate: impl Fn(&QrRequest) -> Result<T, GenerateError>,
+    ) -> Result<T, GenerateError> {
+        let mut result = generate(&self.at_ec_level(EcLevel::H));
+        for ec_level in [EcLevel::Q, EcLevel::M, EcLevel::L] {
+            match result {
+                Err(GenerateError::DataTooLong { .. }
+                    | GenerateError::SymbolTooLong { .. }
+                    | GenerateError::TooLongForStructuredAppend
+                    | GenerateError::UnsupportedEcLevel { .. }) => result = generate(&self.at_ec_level(ec_level)),
+                _ => break,
+            }
+        }
+        result
+    }
+
+    /// Encodes the whole payload as a single symbol.
+    pub fn generate(&self) -> Result<GeneratedQr, GenerateError> {
+        if self.options.auto_ec_level && self.options.symbology.is_qr_family() {
+            return self.maximize_ec_level(QrRequest::generate_single);
+        }
+        self.generate_single().map_err(|e| self.explain(e))
+    }
+
+    // `generate` without working out which lower level would fit
+    fn generate_single(&self) -> Result<GeneratedQr, GenerateError> {
+        match self.options.symbology {
+            Symbology::DataMatrix => return self.generate_data_matrix(),
+            Symbology::Aztec => return self.generate_aztec(),
+            Symbology::Pdf417 => return self.generate_pdf417(),
+            Symbology::Rmqr => return self.generate_rmqr(),
+            Symbology::Code128 | Symbology::Ean13 | Symbology::UpcA => return self.generate_linear(),
+            Symbology::Qr | Symbology::MicroQr => {},
+        }
+
+        let versions = self.versions()?;
+        let payload = self.payload()?;
+        if let Some(segments) = &self.segments {
+            segment::validate_segments(&payload, segments, self.gs1)?;
+        }
+
+        self.generate_symbol(&versions, &payload, self.segments.as_deref(), None)
+    }
+
+    /// Encodes the payload as a single symbol if it fits, or otherwise (when
+    /// enabled) as the shortest Structured Append sequence that holds it.
+    /// Manual segments only apply to a single symbol.
+    /// With an automatic EC level, a single symbol at any level is preferred
+    /// over a sequence at a higher one.
+    pub fn generate_set(&self) -> Result<Vec<GeneratedQr>, GenerateError> {
+        if self.options.auto_ec_level && self.options.symbology.is_qr_family() {
+            return match self.generate() {
+                Err(GenerateError::DataTooLong { .. }) if self.options.structured_append => {
+                    self.maximize_ec_level(QrRequest::generate_set)
+                },
+                result => result.map(|code| vec![code]),
+            };
+        }
+
+        match self.generate_single() {
+            Err(GenerateError::DataTooLong { .. })
+                if self.options.structured_append && self.options.symbology == Symbology::Qr => {},
+            result => return result.map(|code| vec![code]).map_err(|e| self.explain(e)),
+        }
+
+        let versions = self.versions()?;
+        let payload = self.payload()?;
+        let parity = StructuredAppend::parity(&payload);
+
+        (2..=MAX_SYMBOLS)
+            .find_map(|total| {
+                let chunks: Vec<&[u8]> = payload.chunks(payload.len().div_ceil(total)).collect();
+                if chunks.len() != total {
+                    return None;
+                }
+
+                let codes = chunks.into_iter()
+                    .enumerate()
+                    .map(|(index, chunk)| {
+                        let header = StructuredAppend { index: index as u8, total: total as u8, parity };
+                        self.generate_symbol(&versions, chunk, None, Some(header))
+                    })
+                    .collect::<Result<Vec<_>, _>>();
+                match codes {
+                    Err(GenerateError::DataTooLong { .. }) => None,
+                    result => Some(result),
+                }
+            })
+            .unwrap_or(Err(GenerateError::TooLongForStructuredAppend))
+    }
+
+    /// Encodes the payload as the frames of a fountain-coded stream (see
+    /// [`fountain`]) that carry `block_size` bytes each, plus
+    /// `redundancy_percent` extra frames. QR and Micro QR frames all get the
+    /// version of the largest, so the animation keeps its size.
+    pub fn generate_stream(&self, block_size: usize, redundancy_percent: usize) -> Result<Vec<GeneratedQr>, GenerateError> {
+        let payload = self.payload()?;
+        let frames = fountain::encode(&payload, block_size, fountain::frame_count(payload.len(), block_size, redundancy_percent));
+        // the frames are binary, whatever the payload was transcoded from
+        let options = QrOptions { eci: None, kanji_mode: false, structured_append: false, ..self.options };
+        let generate = |options: QrOptions| {
+            frames.iter().map(|frame| QrRequest::new(frame.clone(), options).generate()).collect::<Result<Vec<_>, _>>()
+        };
+
+        let codes = generate(options)?;
+        let largest = codes.iter()
+            .filter_map(|code| match code.version()? {
+                Version::Normal(number) | Version::Micro(number) => Some(number),
+            })
+            .max();
+        match largest {
+            Some(number) if codes.iter().any(|code| code.size() != codes[0].size()) => {
+                generate(QrOptions { min_version: number, max_version: number, ..options })
+            },
+            _ => Ok(codes),
+        }
+    }
+
+    fn generate_symbol(
+        &self,
+        versions: &[Version],
+        payload: &[u8],
+        segments: Option<&[Segment]>,
+        structured_append: Option<StructuredAppend>,
+    ) -> Result<GeneratedQr, GenerateError> {
+        let QrOptions { symbology, ec_level, min_version, max_version, .. } = self.options;
+
+        let mut unsupported_characters = true;
+        let (version, mut bytes, segments) = versions.iter()
+            .find_map(|&version| match encode(payload, version, &self.options, self.gs1, segments, structured_append.is_some()) {
+                Err(QrError::UnsupportedCharacterSet) => None,
+                Err(QrError::DataTooLong) => {
+                    unsupported_characters = false;
+                    None
+                },
+                result => Some(result),
+            })
+            .ok_or_else(|| match symbology {
+                Symbology::MicroQr if unsupported_characters => GenerateError::UnsupportedCharacters { symbology, min_version, max_version },
+                _ => self.too_long(payload, versions),
+            })??;
+
+        if let Some(mask) = self.options.mask {
+            if mask >= mask_count(version) {
+                return Err(GenerateError::InvalidMask(mask));
+            }
+        }
+
+        if let Some(header) = structured_append {
+            header.write_header(&mut bytes);
+        }
+        let (mut data, mut ec) = construct_codewords(&bytes, version, ec_level)?;
+        if (version, ec_level) == (Version::Micro(3), EcLevel::L) {
+            (data, ec) = halve_last_data_codeword(&data, &ec);
+        }
+        let mut canvas = Canvas::new(version, ec_level);
+        canvas.draw_all_functional_patterns();
+        canvas.draw_data(&data, &ec);
+
+        let width = version.width() as usize;
+        let mut candidates: Vec<Vec<Module>> = (0..mask_count(version))
+            .map(|mask| {
+                let mut canvas = canvas.clone();
+                canvas.apply_mask(mask::mask_pattern(version, mask).expect("mask within mask_count"));
+                canvas.into_colors()
+            })
+            .collect();
+        let mask_penalties: Vec<u32> = candidates.iter()
+            .map(|modules| mask::penalty_score(modules, width, version))
+            .collect();
+        let mask = self.options.mask.unwrap_or_else(|| {
+            (0..mask_count(version)).min_by_key(|&mask| mask_penalties[mask as usize]).expect("at least one mask")
+        });
+
+        Ok(GeneratedQr {
+            size: SymbolSize::Qr(version),
+            ec_level: Some(ec_level),
+            width,
+            height: width,
+            modules: candidates.swap_remove(mask as usize),
+            payload: payload.to_vec(),
+            segments,
+            structured_append,
+            gs1: self.gs1,
+            mask: Some(mask),
+            mask_penalties,
+            text: None,
+            options: self.options,
+        })
+    }
+
+    fn generate_data_matrix(&self) -> Result<GeneratedQr, GenerateError> {
+        let payload = self.payload()?;
+        let symbol = datamatrix::encode(&payload, self.options.eci, self.gs1, self.options.data_matrix_shape)?;
+        Ok(GeneratedQr {
+            size: SymbolSize::DataMatrix { rows: symbol.rows, columns: symbol.columns },
+            ec_level: None,
+            width: symbol.columns,
+            height: symbol.rows,
+            modules: symbol.modules,
+            payload: payload.into_owned(),
+            segments: Vec::new(),
+            structured_append: None,
+            gs1: self.gs1,
+            mask: None,
+            mask_penalties: Vec::new(),
+            text: None,
+            options: self.options,
+        })
+    }
+
+    fn generate_aztec(&self) -> Result<GeneratedQr, GenerateError> {
+        let payload = self.payload()?;
+        let QrOptions { eci, aztec_format, aztec_ec_percent, .. } = self.options;
+        let symbol = aztec::encode(&payload, eci, self.gs1, aztec_format, aztec_ec_percent)?;
+        Ok(GeneratedQr {
+            size: SymbolSize::Aztec { compact: symbol.compact, layers: symbol.layers },
+            ec_level: None,
+            width: symbol.width,
+            height: symbol.width,
+            modules: symbol.modules,
+            payload: payload.into_owned(),
+            segments: Vec::new(),
+            structured_append: None,
+            gs1: self.gs1,
+            mask: None,
+            mask_penalties: Vec::new(),
+            text: None,
+            options: self.options,
+        })
+    }
+
+    fn generate_pdf417(&self) -> Result<GeneratedQr, GenerateError> {
+        // PDF417 has no FNC1 to mark GS1 data with
+        if self.gs1 {
+            return Err(GenerateError::Gs1NotSupported(Symbology::Pdf417));
+        }
+        let payload = self.payload()?;
+        let QrOptions { eci, pdf417_columns, pdf417_security_level, .. } = self.options;
+        let symbol = pdf417::encode(&payload, eci, pdf417_columns, pdf417_security_level)?;
+        Ok(GeneratedQr {
+            size: SymbolSize::Pdf417 { rows: symbol.rows, columns: symbol.columns, security_level: symbol.security_level },
+            ec_level: None,
+            width: symbol.width,
+            height: symbol.height,
+            modules: symbol.modules,
+            payload: payload.into_owned(),
+            segments: Vec::new(),
+            structured_append: None,
+            gs1: false,
+            mask: None,
+            mask_penalties: Vec::new(),
+            text: None,
+            options: self.options,
+        })
+    }
+
+    fn generate_rmqr(&self) -> Result<GeneratedQr, GenerateError> {
+        let payload = self.payload()?;
+        let QrOptions { eci, ec_level, rmqr_height, rmqr_width, .. } = self.options;
+        let symbol = rmqr::encode(&payload, eci, self.gs1, self.options.allows_kanji(), ec_level, rmqr_height, rmqr_width)?;
+        Ok(GeneratedQr {
+            size: SymbolSize::Rmqr { height: symbol.height, width: symbol.width },
+            ec_level: Some(ec_level),
+            width: symbol.width,
+            height: symbol.height,
+            modules: symbol.modules,
+            payload: payload.into_owned(),
+            segments: Vec::new(),
+            structured_append: None,
+            gs1: self.gs1,
+            mask: None,
+            mask_penalties: Vec::new(),
+            text: None,
+            options: self.options,
+        })
+    }
+
+    fn generate_linear(&self) -> Result<GeneratedQr, GenerateError> {
+        let symbology = self.options.symbology;
+        if self.gs1 && symbology != Symbology::Code128 {
+            return Err(GenerateError::Gs1NotSupported(symbology));
+        }
+        let payload = self.payload()?;
+        let symbol = linear::encode(symbology, &payload, self.gs1)?;
+        let width = symbol.bars.len();
+        let modules = symbol.bars.iter()
+            .map(|&dark| if dark { Module::Dark } else { Module::Light })
+            .cycle()
+            .take(width * BAR_HEIGHT)
+            .collect();
+        Ok(GeneratedQr {
+            size: SymbolSize::Linear { symbology, modules: width },
+            ec_level: None,
+            width,
+            height: BAR_HEIGHT,
+            modules,
+            payload: payload.into_owned(),
+            segments: Vec::new(),
+            structured_append: None,
+            gs1: self.gs1,
+            mask: None,
+            mask_penalties: Vec::new(),
+            text: self.options.human_readable.then_some(symbol.text),
+            options: self.options,
+        })
+    }
+}
+
+fn encode(
+    data: &[u8],
+    version: Version,
+    options: &QrOptions,
+    gs1: bool,
+    segments: Option<&[Segment]>,
+    structured_append: bool,
+) -> Result<(Version, Vec<u8>, Vec<Segment>), QrError> {
+    let segments = match segments {
+        Some(segments) => segments.to_vec(),
+        None => segment::optimal_segments(data, version, options.allows_kanji(), gs1),
+    };
+
+    let mut bits = Bits::new(version);
+    if structured_append {
+        StructuredAppend::push_placeholder(&mut bits)?;
+    }
+    if let Some(charset) = options.eci {
+        bits.push_eci_designator(charset.eci_designator())?;
+    }
+    if gs1 {
+        bits.push_fnc1_first_position()?;
+    }
+    for segment in &segments {
+        match segment.mode {
+            Mode::Alphanumeric => {
+                bits.push_alphanumeric_data(&segment::alphanumeric_characters(&data[segment.begin..segment.end], gs1))?
+            },
+            _ => bits.push_segments(data, iter::once(*segment))?,
+        }
+    }
+
+    // M1 and M3 end their data on a 4 bit codeword, and `qrcode` underflows
+    // padding a terminator that stops inside it. Everything after the
+    // terminator is zero there, so fill up to the capacity by hand.
+    let capacity = bits.max_len(options.ec_level)?;
+    let terminator = match version {
+        Version::Micro(number) => number as usize * 2 + 1,
+        Version::Normal(_) => 4,
+    };
+    let end = bits.len() + terminator;
+    if !capacity.is_multiple_of(8) && end > capacity / 8 * 8 && end < capacity {
+        let mut bytes = bits.into_bytes();
+        bytes.resize(capacity.div_ceil(8), 0);
+        return Ok((version, bytes, segments));
+    }
+    bits.push_terminator(options.ec_level)?;
+    Ok((version, bits.into_bytes(), segments))
+}
+
+// M3-L ends its data with a 4 bit codeword like M1 and M3-M, but `qrcode`
+// draws it in full, pushing the error correction half a codeword off the
+// symbol. Moving everything after the 4 bits up by a nibble gives the
+// standard layout when drawn in whole bytes; the last nibble never fits.
+fn halve_last_data_codeword(data: &[u8], ec: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let nibbles: Vec<u8> = data.iter()
+        .flat_map(|&byte| [byte >> 4, byte & 0xf])
+        .take(data.len() * 2 - 1)
+        .chain(ec.iter().flat_map(|&byte| [byte >> 4, byte & 0xf]))
+        .chain([0])
+        .collect();
+    let mut bytes: Vec<u8> = nibbles.chunks(2).map(|pair| pair[0] << 4 | pair[1]).collect();
+    let ec = bytes.split_off(data.len());
+    (bytes, ec)
+}
+
+/// An encoded symbol, ready to be rendered. The SVG preview and every export
+/// format are drawn from the same symbol, so they can never disagree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeneratedQr {
+    size: SymbolSize,
+    ec_level: Option<EcLevel>,
+    width: usize,
+    height: usize,
+    modules: Vec<Module>,
+    payload: Vec<u8>,
+    segments: Vec<Segment>,
+    structured_append: Option<StructuredAppend>,
+    gs1: bool,
+    mask: Option<u8>,
+    mask_penalties: Vec<u32>,
+    text: Option<String>,
+    options: QrOptions,
+}
+
+impl GeneratedQr {
+    pub fn size(&self) -> SymbolSize {
+        self.size
+    }
+
+    /// The QR or Micro QR version, or `None` for other symbologies.
+    pub fn version(&self) -> Option<Version> {
+        match self.size {
+            SymbolSize::Qr(version) => Some(version),
+            _ => None,
+        }
+    }
+
+    /// The QR or rMQR error correction level, or `None` for symbologies with their own.
+    pub fn ec_level(&self) -> Option<EcLevel> {
+        self.ec_level
+    }
+
+    /// Number of modules across, not counting the quiet zone.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of modules down, not counting the quiet zone.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The bytes that were encoded, after any ECI transcoding. For part of a
+    /// Structured Append sequence, only this symbol's share.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// The segments the payload was encoded as. Empty for symbologies that
+    /// are not QR.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Bits taken up by the headers and segments, before the terminator and
+    /// padding. Zero for symbologies that are not QR.
+    pub fn data_bits(&self) -> usize {
+        let Some(version) = self.version() else {
+            return 0;
+        };
+        let eci_bits = if self.options.eci.is_some() { capacity::ECI_HEADER_BITS } else { 0 };
+        let structured_append_bits = if self.structured_append.is_some() { structured_append::HEADER_BITS } else { 0 };
+        let fnc1_bits = if self.gs1 { capacity::FNC1_HEADER_BITS } else { 0 };
+        eci_bits + structured_append_bits + fnc1_bits + self.segments.iter()
+            .map(|segment| segment::encoded_len(&self.payload, segment, version, self.gs1))
+            .sum::<usize>()
+    }
+
+    /// Whether the symbol holds GS1 element strings.
+    pub fn is_gs1(&self) -> bool {
+        self.gs1
+    }
+
+    /// This symbol's place in its Structured Append sequence, if it is part of one.
+    pub fn structured_append(&self) -> Option<StructuredAppend> {
+        self.structured_append
+    }
+
+    /// The mask reference number that was applied, if the symbology has masks.
+    pub fn mask(&self) -> Option<u8> {
+        self.mask
+    }
+
+    /// Penalty score of every mask candidate, indexed by mask reference number.
+    pub fn mask_penalties(&self) -> &[u32] {
+        &self.mask_penalties
+    }
+
+    /// The human-readable text printed under a linear barcode, if enabled.
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.width + x] == Module::Dark
+    }
+
+    pub fn options(&self) -> &QrOptions {
+        &self.options
+    }
+}
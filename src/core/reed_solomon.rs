@@ -0,0 +1,214 @@
+//! Reed-Solomon error correction over GF(2^m), shared by the symbologies
+//! whose codewords are not handled by `qrcode` and by the decoder.
+
+/// A Galois field of `2^bits` elements built from a primitive polynomial.
+pub(super) struct GaloisField {
+    exp: Vec<u16>,
+    log: Vec<u16>,
+}
+
+impl GaloisField {
+    /// `primitive` includes the leading term, e.g. `0x12d` for Data Matrix.
+    pub(super) fn new(bits: u32, primitive: u32) -> GaloisField {
+        let size = 1 << bits;
+        let mut exp = vec![0; size];
+        let mut log = vec![0; size];
+        let mut value = 1;
+        for (power, element) in exp.iter_mut().enumerate().take(size - 1) {
+            *element = value as u16;
+            log[value as usize] = power as u16;
+            value <<= 1;
+            if value & size as u32 != 0 {
+                value ^= primitive;
+            }
+        }
+        GaloisField { exp, log }
+    }
+
+    fn order(&self) -> usize {
+        self.exp.len() - 1
+    }
+
+    fn mul(&self, a: u16, b: u16) -> u16 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        self.exp[(self.log[a as usize] as usize + self.log[b as usize] as usize) % self.order()]
+    }
+
+    // `b` must not be zero
+    fn div(&self, a: u16, b: u16) -> u16 {
+        if a == 0 {
+            return 0;
+        }
+        self.exp[(self.log[a as usize] as usize + self.order() - self.log[b as usize] as usize) % self.order()]
+    }
+
+    fn pow(&self, power: usize) -> u16 {
+        self.exp[power % self.order()]
+    }
+
+    // evaluates a polynomial with its lowest power first
+    fn eval(&self, polynomial: &[u16], x: u16) -> u16 {
+        polynomial.iter().rev().fold(0, |sum, &coefficient| self.mul(sum, x) ^ coefficient)
+    }
+
+    // coefficients of (x - a^first)(x - a^(first + 1))..., highest power first
+    fn generator(&self, degree: usize, first_root: usize) -> Vec<u16> {
+        let mut generator = vec![1];
+        for i in 0..degree {
+            let root = self.exp[(first_root + i) % self.order()];
+            let mut next = generator.clone();
+            next.push(0);
+            for (j, &coefficient) in generator.iter().enumerate() {
+                next[j + 1] ^= self.mul(coefficient, root);
+            }
+            generator = next;
+        }
+        generator
+    }
+
+    /// The `ec_len` check codewords for `data`.
+    pub(super) fn ec_codewords(&self, data: &[u16], ec_len: usize, first_root: usize) -> Vec<u16> {
+        let generator = self.generator(ec_len, first_root);
+        let mut remainder = vec![0; ec_len];
+        for &codeword in data {
+            let factor = codeword ^ remainder[0];
+            remainder.rotate_left(1);
+            remainder[ec_len - 1] = 0;
+            for (r, &g) in remainder.iter_mut().zip(&generator[1..]) {
+                *r ^= self.mul(g, factor);
+            }
+        }
+        remainder
+    }
+
+    /// Repairs `codewords`, data followed by `ec_len` check codewords, in
+    /// place. Returns how many codewords were wrong, or `None` if more were
+    /// wrong than the check codewords can locate.
+    pub(super) fn correct(&self, codewords: &mut [u16], ec_len: usize, first_root: usize) -> Option<usize> {
+        // the codewords are a polynomial with the highest power first
+        let syndromes: Vec<u16> = (0..ec_len)
+            .map(|i| codewords.iter().fold(0, |sum, &c| self.mul(sum, self.pow(first_root + i)) ^ c))
+            .collect();
+        if syndromes.iter().all(|&s| s == 0) {
+            return Some(0);
+        }
+
+        // Berlekamp-Massey finds the error locator, lowest power first
+        let mut locator = vec![1];
+        let mut previous = vec![1];
+        let (mut errors, mut shift, mut previous_discrepancy) = (0, 1, 1);
+        for n in 0..ec_len {
+            let discrepancy = (1..=errors).fold(syndromes[n], |d, i| d ^ self.mul(locator[i], syndromes[n - i]));
+            if discrepancy == 0 {
+                shift += 1;
+                continue;
+            }
+            let factor = self.div(discrepancy, previous_discrepancy);
+            let mut next = locator.clone();
+            next.resize(next.len().max(previous.len() + shift), 0);
+            for (i, &p) in previous.iter().enumerate() {
+                next[i + shift] ^= self.mul(factor, p);
+            }
+            if 2 * errors <= n {
+                previous = std::mem::replace(&mut locator, next);
+                errors = n + 1 - errors;
+                previous_discrepancy = discrepancy;
+                shift = 1;
+            } else {
+                locator = next;
+                shift += 1;
+            }
+        }
+        locator.truncate(errors + 1);
+        if 2 * errors > ec_len {
+            return None;
+        }
+
+        // Chien search: the codeword at `index` is wrong when the locator has
+        // a root at the inverse of its position
+        let n = codewords.len();
+        let positions: Vec<usize> = (0..n)
+            .filter(|&index| self.eval(&locator, self.pow(self.order() - (n - 1 - index) % self.order())) == 0)
+            .collect();
+        if positions.len() != errors {
+            return None;
+        }
+
+        // Forney's algorithm gives the size of each error
+        let mut evaluator = vec![0; ec_len];
+        for (i, &s) in syndromes.iter().enumerate() {
+            for (j, &l) in locator.iter().enumerate().take(ec_len - i) {
+                evaluator[i + j] ^= self.mul(s, l);
+            }
+        }
+        let derivative: Vec<u16> = locator.iter().enumerate().skip(1).map(|(i, &l)| if i % 2 == 1 { l } else { 0 }).collect();
+        for index in positions {
+            let power = n - 1 - index;
+            let inverse = self.pow(self.order() - power % self.order());
+            let denominator = self.eval(&derivative, inverse);
+            if denominator == 0 {
+                return None;
+            }
+            let magnitude = self.mul(self.pow(power * (self.order() + 1 - first_root % self.order())), self.div(self.eval(&evaluator, inverse), denominator));
+            codewords[index] ^= magnitude;
+        }
+        Some(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (field, first root) of QR, Data Matrix and Aztec's smallest and largest words
+    fn fields() -> [(GaloisField, usize); 4] {
+        [
+            (GaloisField::new(8, 0x11d), 0),
+            (GaloisField::new(8, 0x12d), 1),
+            (GaloisField::new(4, 0x13), 1),
+            (GaloisField::new(12, 0x1069), 1),
+        ]
+    }
+
+    // data codewords followed by `ec_len` check codewords, at most as many
+    // as the field has nonzero elements
+    fn codeword_block(field: &GaloisField, ec_len: usize, first_root: usize) -> Vec<u16> {
+        let len = (field.order() - ec_len).min(11);
+        let data: Vec<u16> = (0..len).map(|i| ((i * 37 + 11) % (field.order() + 1)) as u16).collect();
+        let ec = field.ec_codewords(&data, ec_len, first_root);
+        data.into_iter().chain(ec).collect()
+    }
+
+    #[test]
+    fn corrects_up_to_half_the_check_codewords() {
+        for (field, first_root) in fields() {
+            let ec_len = 10;
+            let original = codeword_block(&field, ec_len, first_root);
+            assert_eq!(field.correct(&mut original.clone(), ec_len, first_root), Some(0));
+
+            for errors in 1..=ec_len / 2 {
+                let mut damaged = original.clone();
+                for i in 0..errors {
+                    damaged[i * 2 + 1] ^= (i as u16 % field.order() as u16) + 1;
+                }
+                assert_eq!(field.correct(&mut damaged, ec_len, first_root), Some(errors));
+                assert_eq!(damaged, original);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_one_error_too_many() {
+        for (field, first_root) in fields() {
+            let ec_len = 10;
+            let original = codeword_block(&field, ec_len, first_root);
+            let mut damaged = original.clone();
+            for i in 0..=ec_len / 2 {
+                damaged[i * 2] ^= 1;
+            }
+            assert_eq!(field.correct(&mut damaged, ec_len, first_root), None);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use std::convert::Infallible;
+use std::fmt::{self, Write};
+
+use embedded_graphics::mono_font::ascii::FONT_6X10;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::{DrawTarget, OriginDimensions, Pixel, Point, Size};
+use embedded_graphics::text::{Baseline, Text};
+use embedded_graphics::Drawable;
+use gif::{Encoder, Frame, Repeat};
+use image::{DynamicImage, ImageOutputFormat, Rgb, RgbImage};
+
+use super::GeneratedQr;
+
+fn to_array<T>(xs: (T, T, T)) -> [T; 3] { [xs.0, xs.1, xs.2] }
+
+/// Pixels per module used for raster output unless the caller asks otherwise.
+pub const DEFAULT_MODULE_SIZE: u32 = 8;
+
+/// How long each frame of an animation stays up unless the caller asks otherwise.
+pub const DEFAULT_FRAME_DELAY_MS: u32 = 250;
+
+/// The widest quiet zone, in modules, the options may ask for.
+pub const MAX_QUIET_ZONE: usize = 20;
+
+// the font human-readable text is drawn in, one module per pixel at most
+const GLYPH_WIDTH: usize = 6;
+const GLYPH_HEIGHT: usize = 10;
+const GLYPH_BASELINE: usize = 8;
+// modules between the bars and their text
+const TEXT_GAP: usize = 2;
+
+/// Text drawn in the built-in bitmap font, one entry per pixel.
+struct TextBitmap {
+    width: usize,
+    pixels: Vec<bool>,
+}
+
+impl TextBitmap {
+    fn new(text: &str) -> TextBitmap {
+        let width = text.chars().count() * GLYPH_WIDTH;
+        let mut bitmap = TextBitmap { width, pixels: vec![false; width * GLYPH_HEIGHT] };
+        Text::with_baseline(text, Point::zero(), MonoTextStyle::new(&FONT_6X10, BinaryColor::On), Baseline::Top)
+            .draw(&mut bitmap)
+            .expect("drawing to a bitmap cannot fail");
+        bitmap
+    }
+
+    fn is_set(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < GLYPH_HEIGHT && self.pixels[y * self.width + x]
+    }
+}
+
+impl OriginDimensions for TextBitmap {
+    fn size(&self) -> Size {
+        Size::new(self.width as u32, GLYPH_HEIGHT as u32)
+    }
+}
+
+impl DrawTarget for TextBitmap {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    fn draw_iter<I: IntoIterator<Item = Pixel<BinaryColor>>>(&mut self, pixels: I) -> Result<(), Infallible> {
+        for Pixel(point, color) in pixels {
+            let (x, y) = (point.x as usize, point.y as usize);
+            if color.is_on() && x < self.width && y < GLYPH_HEIGHT {
+                self.pixels[y * self.width + x] = true;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// The file formats a generated code can be written as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat { Svg, Png, Jpeg }
+
+impl ImageFormat {
+    pub fn from_extension(extension: &str) -> Option<ImageFormat> {
+        match extension.to_ascii_lowercase().as_str() {
+            "svg" => Some(ImageFormat::Svg),
+            "png" => Some(ImageFormat::Png),
+            "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+            _ => None
+        }
+    }
+
+    pub fn mime(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Svg => "image/svg+xml",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpeg",
+            ImageFormat::Svg => "svg",
+        }
+    }
+}
+
+impl GeneratedQr {
+    /// Modules of blank margin drawn around the symbol: the one set in the
+    /// options, or else the one its symbology asks for.
+    pub fn quiet_zone(&self) -> usize {
+        self.options.quiet_zone.unwrap_or(self.options.symbology.quiet_zone())
+    }
+
+    // the symbol's size with its quiet zone and any text, and whether the
+    // module at a position of that area is dark
+    fn canvas(&self) -> (usize, usize, impl Fn(usize, usize) -> bool + '_) {
+        let quiet_zone = self.quiet_zone();
+        let text_band = if self.text.is_some() { TEXT_GAP + GLYPH_HEIGHT } else { 0 };
+        let is_dark = move |x: usize, y: usize| {
+            let (x, y) = (x.wrapping_sub(quiet_zone), y.wrapping_sub(quiet_zone));
+            x < self.width && y < self.height && self.is_dark(x, y)
+        };
+        (self.width + 2 * quiet_zone, self.height + text_band + 2 * quiet_zone, is_dark)
+    }
+
+    // the left and top edge of the text, in modules, and the modules per font
+    // pixel, which shrinks text wider than the bars
+    fn text_layout(&self, text: &TextBitmap) -> (f64, f64, f64) {
+        let quiet_zone = self.quiet_zone() as f64;
+        let scale = (self.width as f64 / text.width as f64).min(1.0);
+        let left = quiet_zone + (self.width as f64 - text.width as f64 * scale) / 2.0;
+        (left, quiet_zone + (self.height + TEXT_GAP) as f64, scale)
+    }
+
+    /// Renders the symbol as an SVG document, one user unit per module.
+    pub fn to_svg(&self) -> String {
+        let (width, height, is_dark) = self.canvas();
+        let mut svg = format!(
+            r#"<?xml version="1.0" standalone="yes"?><svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="{width}" height="{height}" viewBox="0 0 {width} {height}" shape-rendering="crispEdges"><rect x="0" y="0" width="{width}" height="{height}" fill="{}"/><path fill="{}" d=""#,
+            self.options.background_color.to_hex(),
+            self.options.foreground_color.to_hex(),
+        );
+        for y in 0..height {
+            for x in (0..width).filter(|&x| is_dark(x, y)) {
+                write!(svg, "M{x} {y}h1v1H{x}V{y}").expect("writing to a String cannot fail");
+            }
+        }
+        svg.push_str(r#""/>"#);
+        if let Some(text) = &self.text {
+            let (left, top, scale) = self.text_layout(&TextBitmap::new(text));
+            write!(
+                svg,
+                r#"<text x="{left}" y="{}" font-family="monospace" font-size="{}" textLength="{}" lengthAdjust="spacingAndGlyphs" fill="{}">{}</text>"#,
+                top + GLYPH_BASELINE as f64 * scale,
+                GLYPH_HEIGHT as f64 * scale,
+                (text.chars().count() * GLYPH_WIDTH) as f64 * scale,
+                self.options.foreground_color.to_hex(),
+                escape_xml(text),
+            ).expect("writing to a String cannot fail");
+        }
+        svg.push_str("</svg>");
+        svg
+    }
+
+    /// Renders the symbol as an image with `module_size` pixels per module.
+    pub fn to_image(&self, module_size: u32) -> RgbImage {
+        let (width, height, is_dark) = self.canvas();
+        let light = Rgb(to_array(self.options.background_color.to_rgb_u8()));
+        let dark = Rgb(to_array(self.options.foreground_color.to_rgb_u8()));
+        let text = self.text.as_deref().map(TextBitmap::new);
+        let layout = text.as_ref().map(|text| self.text_layout(text));
+        let is_text = |x: u32, y: u32| match (&text, layout) {
+            (Some(text), Some((left, top, scale))) => {
+                let (x, y) = (x as f64 / module_size as f64 - left, y as f64 / module_size as f64 - top);
+                x >= 0.0 && y >= 0.0 && text.is_set((x / scale) as usize, (y / scale) as usize)
+            },
+            _ => false,
+        };
+        RgbImage::from_fn(width as u32 * module_size, height as u32 * module_size, |x, y| {
+            if is_dark((x / module_size) as usize, (y / module_size) as usize) || is_text(x, y) { dark } else { light }
+        })
+    }
+
+    /// Encodes the symbol as a file of the given format. `module_size` is the
+    /// number of pixels per module of raster formats and is ignored for SVG.
+    pub fn encode(&self, format: ImageFormat, module_size: u32) -> Vec<u8> {
+        let output_format = match format {
+            ImageFormat::Svg => return self.to_svg().into_bytes(),
+            ImageFormat::Png => ImageOutputFormat::Png,
+            ImageFormat::Jpeg => ImageOutputFormat::Jpeg(70),
+        };
+
+        let mut buffer = Vec::new();
+        DynamicImage::ImageRgb8(self.to_image(module_size))
+            .write_to(&mut buffer, output_format)
+            .expect("Failed to write image to buffer");
+        buffer
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RenderError {
+    /// GIF frames are at most 65535 pixels on a side.
+    FrameTooLarge { width: u32, height: u32 },
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::FrameTooLarge { width, height } => {
+                write!(f, "The frames would be {width}×{height} pixels, but GIF frames are at most {} on a side. Use fewer pixels per module.", u16::MAX)
+            },
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+/// Encodes `codes` as a looping animated GIF, each frame shown for
+/// `frame_delay_ms`. Smaller symbols are centered on the largest one's canvas,
+/// and the colors come from the first.
+pub fn encode_gif(codes: &[GeneratedQr], module_size: u32, frame_delay_ms: u32) -> Result<Vec<u8>, RenderError> {
+    let images: Vec<RgbImage> = codes.iter().map(|code| code.to_image(module_size)).collect();
+    let width = images.iter().map(RgbImage::width).max().unwrap_or(0);
+    let height = images.iter().map(RgbImage::height).max().unwrap_or(0);
+    let (Ok(frame_width), Ok(frame_height)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(RenderError::FrameTooLarge { width, height });
+    };
+    let (light, dark) = match codes.first() {
+        Some(code) => (code.options.background_color.to_rgb_u8(), code.options.foreground_color.to_rgb_u8()),
+        None => ((255, 255, 255), (0, 0, 0)),
+    };
+    let palette = [light.0, light.1, light.2, dark.0, dark.1, dark.2];
+
+    let mut buffer = Vec::new();
+    {
+        let mut encoder = Encoder::new(&mut buffer, frame_width, frame_height, &palette).expect("Failed to start GIF");
+        encoder.set_repeat(Repeat::Infinite).expect("Failed to write GIF");
+        for image in &images {
+            let (left, top) = ((width - image.width()) / 2, (height - image.height()) / 2);
+            let pixels: Vec<u8> = (0..width * height)
+                .map(|i| {
+                    let (x, y) = ((i % width).wrapping_sub(left), (i / width).wrapping_sub(top));
+                    let is_dark = x < image.width() && y < image.height() && image.get_pixel(x, y).0 == to_array(dark);
+                    is_dark as u8
+                })
+                .collect();
+            let mut frame = Frame::from_indexed_pixels(frame_width, frame_height, &pixels, None);
+            frame.delay = (frame_delay_ms / 10) as u16;
+            encoder.write_frame(&frame).expect("Failed to write GIF");
+        }
+    }
+    Ok(buffer)
+}
@@ -0,0 +1,427 @@
+//! Rectangular Micro QR (ISO/IEC 23941): the QR encoding modes and Reed-Solomon
+//! codewords, laid out in 32 sizes from 7×43 to 17×139 modules.
+
+use std::borrow::Cow;
+
+use qrcode::types::{Color as Module, EcLevel};
+
+use super::reed_solomon::GaloisField;
+use super::segment::{self, Mode, Segment};
+use super::{Charset, GenerateError, Symbology};
+
+/// The heights rMQR symbols come in, in modules.
+pub const RMQR_HEIGHTS: [usize; 6] = [7, 9, 11, 13, 15, 17];
+/// The widths rMQR symbols come in, in modules. Only heights 11 and 13 are
+/// 27 modules wide.
+pub const RMQR_WIDTHS: [usize; 6] = [27, 43, 59, 77, 99, 139];
+
+const ECI_INDICATOR: usize = 0b111;
+const FNC1_FIRST_INDICATOR: usize = 0b101;
+const PAD_CODEWORDS: [u8; 2] = [0xec, 0x11];
+
+// x^12 + x^11 + x^10 + x^9 + x^8 + x^5 + x^2 + 1
+const FORMAT_GENERATOR: u32 = 0x1f25;
+const FORMAT_MASK_FINDER_SIDE: u32 = 0b011111101010110010;
+const FORMAT_MASK_SUB_FINDER_SIDE: u32 = 0b100000101001111011;
+
+/// The error correction of one level: `count` blocks of `data` codewords,
+/// each followed by `ec_per_block` check codewords.
+struct Blocks {
+    ec_per_block: usize,
+    groups: [(usize, usize); 2],
+}
+
+const fn blocks(ec_per_block: usize, count: usize, data: usize, longer_count: usize) -> Blocks {
+    Blocks { ec_per_block, groups: [(count, data), (longer_count, data + 1)] }
+}
+
+struct Size {
+    height: usize,
+    width: usize,
+    /// Character count indicator lengths for Numeric, Alphanumeric, Byte and Kanji.
+    count_bits: [usize; 4],
+    m: Blocks,
+    h: Blocks,
+}
+
+const fn size(height: usize, width: usize, count_bits: [usize; 4], m: Blocks, h: Blocks) -> Size {
+    Size { height, width, count_bits, m, h }
+}
+
+// in version indicator order
+const SIZES: [Size; 32] = [
+    size(7, 43, [4, 3, 3, 2], blocks(7, 1, 6, 0), blocks(10, 1, 3, 0)),
+    size(7, 59, [5, 5, 4, 3], blocks(9, 1, 12, 0), blocks(14, 1, 7, 0)),
+    size(7, 77, [6, 5, 5, 4], blocks(12, 1, 20, 0), blocks(22, 1, 10, 0)),
+    size(7, 99, [7, 6, 5, 5], blocks(16, 1, 28, 0), blocks(30, 1, 14, 0)),
+    size(7, 139, [7, 6, 6, 5], blocks(24, 1, 44, 0), blocks(22, 2, 12, 0)),
+    size(9, 43, [5, 5, 4, 3], blocks(9, 1, 12, 0), blocks(14, 1, 7, 0)),
+    size(9, 59, [6, 5, 5, 4], blocks(12, 1, 21, 0), blocks(22, 1, 11, 0)),
+    size(9, 77, [7, 6, 5, 5], blocks(18, 1, 31, 0), blocks(16, 1, 8, 1)),
+    size(9, 99, [7, 6, 6, 5], blocks(24, 1, 42, 0), blocks(22, 2, 11, 0)),
+    size(9, 139, [8, 7, 6, 6], blocks(18, 1, 31, 1), blocks(22, 3, 11, 0)),
+    size(11, 27, [4, 4, 3, 2], blocks(8, 1, 7, 0), blocks(10, 1, 5, 0)),
+    size(11, 43, [6, 5, 5, 4], blocks(12, 1, 19, 0), blocks(20, 1, 11, 0)),
+    size(11, 59, [7, 6, 5, 5], blocks(16, 1, 31, 0), blocks(16, 1, 7, 1)),
+    size(11, 77, [7, 6, 6, 5], blocks(24, 1, 43, 0), blocks(22, 1, 11, 1)),
+    size(11, 99, [8, 7, 6, 6], blocks(16, 1, 28, 1), blocks(30, 1, 14, 1)),
+    size(11, 139, [8, 7, 7, 6], blocks(24, 2, 42, 0), blocks(30, 3, 14, 0)),
+    size(13, 27, [5, 5, 4, 3], blocks(9, 1, 12, 0), blocks(14, 1, 7, 0)),
+    size(13, 43, [6, 6, 5, 5], blocks(14, 1, 27, 0), blocks(28, 1, 13, 0)),
+    size(13, 59, [7, 6, 6, 5], blocks(22, 1, 38, 0), blocks(20, 2, 10, 0)),
+    size(13, 77, [7, 7, 6, 5], blocks(16, 1, 26, 1), blocks(28, 1, 14, 1)),
+    size(13, 99, [8, 7, 7, 6], blocks(20, 1, 36, 1), blocks(26, 1, 11, 2)),
+    size(13, 139, [8, 8, 7, 6], blocks(20, 2, 35, 1), blocks(28, 2, 13, 2)),
+    size(15, 43, [7, 6, 6, 5], blocks(18, 1, 33, 0), blocks(18, 1, 7, 1)),
+    size(15, 59, [7, 7, 6, 5], blocks(26, 1, 48, 0), blocks(24, 2, 13, 0)),
+    size(15, 77, [8, 7, 7, 6], blocks(18, 1, 33, 1), blocks(24, 2, 10, 1)),
+    size(15, 99, [8, 7, 7, 6], blocks(24, 2, 44, 0), blocks(22, 4, 12, 0)),
+    size(15, 139, [9, 8, 7, 7], blocks(24, 2, 42, 1), blocks(26, 1, 13, 4)),
+    size(17, 43, [7, 6, 6, 5], blocks(22, 1, 39, 0), blocks(20, 1, 10, 1)),
+    size(17, 59, [8, 7, 6, 6], blocks(16, 2, 28, 0), blocks(30, 2, 14, 0)),
+    size(17, 77, [8, 7, 7, 6], blocks(22, 2, 39, 0), blocks(28, 1, 12, 2)),
+    size(17, 99, [8, 8, 7, 6], blocks(20, 2, 33, 1), blocks(26, 4, 14, 0)),
+    size(17, 139, [9, 8, 8, 7], blocks(20, 4, 38, 0), blocks(26, 2, 12, 4)),
+];
+
+// center columns of the alignment patterns on the top and bottom edge
+fn alignment_columns(width: usize) -> &'static [usize] {
+    match width {
+        43 => &[21],
+        59 => &[19, 39],
+        77 => &[25, 51],
+        99 => &[23, 49, 75],
+        139 => &[27, 55, 83, 111],
+        _ => &[],
+    }
+}
+
+impl Size {
+    fn blocks(&self, ec_level: EcLevel) -> &Blocks {
+        match ec_level {
+            EcLevel::H => &self.h,
+            _ => &self.m,
+        }
+    }
+
+    fn data_codewords(&self, ec_level: EcLevel) -> usize {
+        self.blocks(ec_level).groups.iter().map(|&(count, data)| count * data).sum()
+    }
+}
+
+/// An encoded rMQR symbol, row by row.
+pub(super) struct Rmqr {
+    pub height: usize,
+    pub width: usize,
+    pub modules: Vec<Module>,
+}
+
+#[derive(Default)]
+struct BitBuffer(Vec<bool>);
+
+impl BitBuffer {
+    fn push(&mut self, value: usize, bits: usize) {
+        self.0.extend((0..bits).rev().map(|i| value >> i & 1 == 1));
+    }
+}
+
+fn alphanumeric_value(byte: u8) -> usize {
+    match byte {
+        b'0'..=b'9' => (byte - b'0') as usize,
+        b'A'..=b'Z' => (byte - b'A') as usize + 10,
+        _ => 36 + b" $%*+-./:".iter().position(|&c| c == byte).expect("alphanumeric segment"),
+    }
+}
+
+// the bit stream for one size, or `None` if a segment overflows its character count
+fn encode_segments(data: &[u8], segments: &[Segment], eci: Option<Charset>, gs1: bool, size: &Size) -> Option<BitBuffer> {
+    let mut bits = BitBuffer::default();
+    if let Some(charset) = eci {
+        // every designator `Charset` has is below 128 and takes 8 bits
+        bits.push(ECI_INDICATOR, 3);
+        bits.push(charset.eci_designator() as usize, 8);
+    }
+    if gs1 {
+        bits.push(FNC1_FIRST_INDICATOR, 3);
+    }
+
+    for segment in segments {
+        let part = match segment.mode {
+            Mode::Alphanumeric => segment::alphanumeric_characters(&data[segment.begin..segment.end], gs1),
+            _ => Cow::Borrowed(&data[segment.begin..segment.end]),
+        };
+        let part = &*part;
+        let (indicator, count_bits, count) = match segment.mode {
+            Mode::Numeric => (0b001, size.count_bits[0], part.len()),
+            Mode::Alphanumeric => (0b010, size.count_bits[1], part.len()),
+            Mode::Byte => (0b011, size.count_bits[2], part.len()),
+            Mode::Kanji => (0b100, size.count_bits[3], part.len() / 2),
+        };
+        if count >= 1 << count_bits {
+            return None;
+        }
+        bits.push(indicator, 3);
+        bits.push(count, count_bits);
+
+        match segment.mode {
+            Mode::Numeric => for digits in part.chunks(3) {
+                let value = digits.iter().fold(0, |value, digit| value * 10 + (digit - b'0') as usize);
+                bits.push(value, digits.len() * 3 + 1);
+            },
+            Mode::Alphanumeric => for pair in part.chunks(2) {
+                match *pair {
+                    [first, second] => bits.push(alphanumeric_value(first) * 45 + alphanumeric_value(second), 11),
+                    [single] => bits.push(alphanumeric_value(single), 6),
+                    _ => unreachable!(),
+                }
+            },
+            Mode::Byte => for &byte in part {
+                bits.push(byte.into(), 8);
+            },
+            Mode::Kanji => for pair in part.chunks(2) {
+                let code = u16::from_be_bytes([pair[0], pair[1]]) as usize;
+                let code = code - if code <= 0x9ffc { 0x8140 } else { 0xc140 };
+                bits.push((code >> 8) * 0xc0 + (code & 0xff), 13);
+            },
+        }
+    }
+    Some(bits)
+}
+
+/// Encodes `data` in the smallest rMQR symbol with the requested `height` and
+/// `width` (any, if `None`) that holds it at `ec_level`, which must be M or H.
+pub(super) fn encode(
+    data: &[u8],
+    eci: Option<Charset>,
+    gs1: bool,
+    allow_kanji: bool,
+    ec_level: EcLevel,
+    height: Option<usize>,
+    width: Option<usize>,
+) -> Result<Rmqr, GenerateError> {
+    if !matches!(ec_level, EcLevel::M | EcLevel::H) {
+        return Err(GenerateError::UnsupportedEcLevel { symbology: Symbology::Rmqr, ec_level, min_version: 1, max_version: 1 });
+    }
+    let mut sizes: Vec<(usize, &Size)> = SIZES.iter()
+        .enumerate()
+        .filter(|(_, size)| height.is_none_or(|height| height == size.height) && width.is_none_or(|width| width == size.width))
+        .collect();
+    if sizes.is_empty() {
+        return Err(GenerateError::UnsupportedRmqrSize { height, width });
+    }
+    sizes.sort_by_key(|(_, size)| size.height * size.width);
+
+    // the optimizer's choice barely depends on the count lengths, which are
+    // close to those of the smallest QR versions
+    let segments = segment::optimal_segments(data, qrcode::Version::Normal(1), allow_kanji, gs1);
+    let encoded = sizes.iter().find_map(|&(index, size)| {
+        let bits = encode_segments(data, &segments, eci, gs1, size)?;
+        (bits.0.len() <= size.data_codewords(ec_level) * 8).then_some((index, size, bits))
+    });
+    let Some((index, size, mut bits)) = encoded else {
+        let largest = sizes.iter().map(|(_, size)| size).max_by_key(|size| size.data_codewords(ec_level)).expect("sizes is not empty");
+        let length = encode_segments(data, &segments, eci, gs1, largest).map_or(data.len(), |bits| bits.0.len().div_ceil(8));
+        return Err(GenerateError::SymbolTooLong { symbology: Symbology::Rmqr, length, limit: largest.data_codewords(ec_level) });
+    };
+
+    let capacity = size.data_codewords(ec_level) * 8;
+    bits.push(0, 3.min(capacity - bits.0.len()));
+    bits.push(0, (8 - bits.0.len() % 8) % 8);
+    let mut codewords: Vec<u8> = bits.0.chunks(8)
+        .map(|byte| byte.iter().fold(0, |codeword, &bit| codeword << 1 | bit as u8))
+        .collect();
+    codewords.extend(PAD_CODEWORDS.iter().cycle().take(capacity / 8 - codewords.len()));
+
+    let codewords = add_error_correction(&codewords, size.blocks(ec_level));
+    let format = format_information(index, ec_level);
+    Ok(Rmqr { height: size.height, width: size.width, modules: draw(&codewords, size, format) })
+}
+
+// splits the data into blocks and interleaves their data and check codewords
+fn add_error_correction(data: &[u8], blocks: &Blocks) -> Vec<u8> {
+    let field = GaloisField::new(8, 0x11d);
+    let mut data_blocks: Vec<Vec<u16>> = Vec::new();
+    let mut rest = data;
+    for &(count, len) in &blocks.groups {
+        for _ in 0..count {
+            let (block, remainder) = rest.split_at(len);
+            data_blocks.push(block.iter().map(|&codeword| codeword.into()).collect());
+            rest = remainder;
+        }
+    }
+    let ec_blocks: Vec<Vec<u16>> = data_blocks.iter()
+        .map(|block| field.ec_codewords(block, blocks.ec_per_block, 0))
+        .collect();
+
+    let longest = data_blocks.iter().map(Vec::len).max().unwrap_or(0);
+    let mut interleaved = Vec::new();
+    for i in 0..longest {
+        interleaved.extend(data_blocks.iter().filter_map(|block| block.get(i)).map(|&codeword| codeword as u8));
+    }
+    for i in 0..blocks.ec_per_block {
+        interleaved.extend(ec_blocks.iter().map(|block| block[i] as u8));
+    }
+    interleaved
+}
+
+// 6 bits of EC level and version indicator, followed by 12 BCH check bits
+fn format_information(index: usize, ec_level: EcLevel) -> u32 {
+    let data = ((ec_level == EcLevel::H) as u32) << 5 | index as u32;
+    let mut remainder = data << 12;
+    for bit in (12..18).rev() {
+        if remainder >> bit & 1 == 1 {
+            remainder ^= FORMAT_GENERATOR << (bit - 12);
+        }
+    }
+    data << 12 | remainder
+}
+
+fn draw(codewords: &[u8], size: &Size, format: u32) -> Vec<Module> {
+    let (height, width) = (size.height, size.width);
+    let mut modules: Vec<Option<bool>> = vec![None; height * width];
+    let mut set = |x: usize, y: usize, dark: bool| modules[y * width + x] = Some(dark);
+
+    // finder pattern and its separator, which only runs below it on taller symbols
+    for y in 0..height.min(8) {
+        for x in 0..8usize {
+            let ring = x.abs_diff(3).max(y.abs_diff(3));
+            set(x, y, ring != 2 && ring < 4);
+        }
+    }
+    // finder sub-pattern in the opposite corner
+    for y in 0..5 {
+        for x in 0..5 {
+            set(width - 5 + x, height - 5 + y, x.abs_diff(2).max(y.abs_diff(2)) != 1);
+        }
+    }
+    for &column in alignment_columns(width) {
+        for center in [1, height - 2] {
+            for y in center - 1..=center + 1 {
+                for x in column - 1..=column + 1 {
+                    set(x, y, (x, y) != (column, center));
+                }
+            }
+        }
+    }
+    // corner finder patterns
+    for (x, y, dark) in [(width - 1, 0, true), (width - 2, 0, true), (width - 1, 1, true), (width - 2, 1, false), (0, height - 1, true), (1, height - 1, true), (2, height - 1, true)] {
+        set(x, y, dark);
+    }
+    if height >= 11 {
+        set(0, height - 2, true);
+        set(1, height - 2, false);
+    }
+
+    // timing patterns along every edge and down each alignment column
+    for x in 0..width {
+        for y in [0, height - 1] {
+            if modules[y * width + x].is_none() {
+                modules[y * width + x] = Some(x % 2 == 0);
+            }
+        }
+    }
+    for &x in [0, width - 1].iter().chain(alignment_columns(width)) {
+        for y in 0..height {
+            if modules[y * width + x].is_none() {
+                modules[y * width + x] = Some(y % 2 == 0);
+            }
+        }
+    }
+
+    // format information beside the finder pattern and the sub-pattern
+    let finder_side = format ^ FORMAT_MASK_FINDER_SIDE;
+    let sub_finder_side = format ^ FORMAT_MASK_SUB_FINDER_SIDE;
+    for bit in 0..18 {
+        let (x, y) = if bit < 15 { (8 + bit / 5, 1 + bit % 5) } else { (11, bit - 14) };
+        modules[y * width + x] = Some(finder_side >> bit & 1 == 1);
+        let (x, y) = if bit < 15 { (width - 8 + bit / 5, height - 6 + bit % 5) } else { (width - 20 + bit, height - 6) };
+        modules[y * width + x] = Some(sub_finder_side >> bit & 1 == 1);
+    }
+
+    // data in two-module columns from the right, zigzagging up and down, with
+    // the single mask rMQR has; unfilled remainder modules stay light
+    let mut bits = codewords.iter().flat_map(|&codeword| (0..8).rev().map(move |i| codeword >> i & 1 == 1));
+    let mut upward = true;
+    for right in (1..width - 1).rev().step_by(2) {
+        for step in 0..height {
+            let y = if upward { height - 1 - step } else { step };
+            for x in [right, right - 1] {
+                let module = &mut modules[y * width + x];
+                if module.is_none() {
+                    let masked = (y / 2 + x / 3) % 2 == 0;
+                    *module = Some(bits.next().unwrap_or(false) != masked);
+                }
+            }
+        }
+        upward = !upward;
+    }
+
+    modules.into_iter().map(|dark| if dark == Some(true) { Module::Dark } else { Module::Light }).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codewords_fill_every_size() {
+        for size in &SIZES {
+            let total = |blocks: &Blocks| blocks.groups.iter().map(|&(count, data)| count * (data + blocks.ec_per_block)).sum::<usize>();
+            assert_eq!(total(&size.m), total(&size.h), "R{}x{}", size.height, size.width);
+            // the modules codeword bits land in are those that change with them
+            let codewords = total(&size.m);
+            let (ones, zeros) = (draw(&vec![0xff; codewords], size, 0), draw(&vec![0; codewords], size, 0));
+            let data_modules = ones.iter().zip(&zeros).filter(|(one, zero)| one != zero).count();
+            assert_eq!(data_modules, codewords * 8, "R{}x{}", size.height, size.width);
+            // the remainder modules left over are fewer than a codeword
+            let mut extra = vec![0; codewords];
+            extra.push(0xff);
+            let spare = draw(&extra, size, 0).iter().zip(&zeros).filter(|(more, zero)| more != zero).count();
+            assert!(spare < 8, "R{}x{}", size.height, size.width);
+        }
+    }
+
+    #[test]
+    fn format_information_words_are_far_apart() {
+        let words: Vec<u32> = (0..SIZES.len()).flat_map(|index| [EcLevel::M, EcLevel::H].map(|level| format_information(index, level))).collect();
+        for (i, a) in words.iter().enumerate() {
+            for b in &words[i + 1..] {
+                assert!((a ^ b).count_ones() >= 8, "{a:018b} and {b:018b}");
+            }
+        }
+    }
+
+    #[test]
+    fn picks_the_smallest_size_allowed() {
+        // R11x27 has fewer modules than R7x43
+        let rmqr = encode(b"HELLO", None, false, false, EcLevel::M, None, None).unwrap();
+        assert_eq!((rmqr.height, rmqr.width), (11, 27));
+        let rmqr = encode(b"HELLO", None, false, false, EcLevel::M, Some(7), None).unwrap();
+        assert_eq!((rmqr.height, rmqr.width), (7, 43));
+        let rmqr = encode(b"HELLO", None, false, false, EcLevel::H, Some(13), None).unwrap();
+        assert_eq!((rmqr.height, rmqr.width), (13, 27));
+        let rmqr = encode(&[b'A'; 60], None, false, false, EcLevel::M, Some(7), None).unwrap();
+        assert_eq!((rmqr.height, rmqr.width), (7, 139));
+        assert_eq!(
+            encode(b"HELLO", None, false, false, EcLevel::M, Some(7), Some(27)).err(),
+            Some(GenerateError::UnsupportedRmqrSize { height: Some(7), width: Some(27) }),
+        );
+        assert!(matches!(encode(&[0; 300], None, false, false, EcLevel::M, None, None), Err(GenerateError::SymbolTooLong { .. })));
+    }
+
+    #[test]
+    fn finder_pattern_is_in_the_top_left_corner() {
+        let rmqr = encode(b"12345", None, false, false, EcLevel::M, None, None).unwrap();
+        let dark = |x: usize, y: usize| rmqr.modules[y * rmqr.width + x] == Module::Dark;
+        for y in 0..7 {
+            let row: String = (0..8).map(|x| if dark(x, y) { '#' } else { '.' }).collect();
+            let expected = match y {
+                0 | 6 => "#######.",
+                1 | 5 => "#.....#.",
+                _ => "#.###.#.",
+            };
+            assert_eq!(row, expected, "row {y}");
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Encoding-mode segmentation. By default the payload is split by `qrcode`'s
+//! optimizer, but a request may carry its own segments to override it.
+
+use std::borrow::Cow;
+
+pub use qrcode::optimize::Segment;
+pub use qrcode::types::Mode;
+
+use qrcode::optimize::Parser;
+use qrcode::types::Version;
+
+use super::GenerateError;
+
+pub const ALL_MODES: [Mode; 4] = [Mode::Numeric, Mode::Alphanumeric, Mode::Byte, Mode::Kanji];
+
+pub fn mode_name(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Numeric => "Numeric",
+        Mode::Alphanumeric => "Alphanumeric",
+        Mode::Byte => "Byte",
+        Mode::Kanji => "Kanji",
+    }
+}
+
+fn is_alphanumeric(byte: u8, gs1: bool) -> bool {
+    matches!(byte, b'0'..=b'9' | b'A'..=b'Z' | b' ' | b'$' | b'%' | b'*' | b'+' | b'-' | b'.' | b'/' | b':') || gs1 && byte == 0x1d
+}
+
+/// Whether a Shift-JIS double byte character is in the range Kanji mode covers.
+pub fn is_kanji(first: u8, second: u8) -> bool {
+    let code = u16::from_be_bytes([first, second]);
+    matches!(code, 0x8140..=0x9ffc | 0xe040..=0xebbf) && matches!(second, 0x40..=0xfc) && second != 0x7f
+}
+
+/// Whether every byte of `data` can be represented in `mode`. In GS1 symbols
+/// Alphanumeric mode also holds the GS separator, written as `%`.
+pub fn mode_accepts(mode: Mode, data: &[u8], gs1: bool) -> bool {
+    match mode {
+        Mode::Numeric => data.iter().all(u8::is_ascii_digit),
+        Mode::Alphanumeric => data.iter().all(|&byte| is_alphanumeric(byte, gs1)),
+        Mode::Byte => true,
+        Mode::Kanji => data.len().is_multiple_of(2) && data.chunks(2).all(|pair| is_kanji(pair[0], pair[1])),
+    }
+}
+
+/// The segments `qrcode`'s optimizer picks for `data` in `version`. Unless
+/// `data` is Shift-JIS, its Kanji segments are turned back into bytes: the
+/// optimizer takes any byte pair in the Kanji range for a Kanji character,
+/// which scanners would then decode into the wrong text.
+pub fn optimal_segments(data: &[u8], version: Version, allow_kanji: bool, gs1: bool) -> Vec<Segment> {
+    // the optimizer only knows the plain Alphanumeric set, so in GS1 symbols
+    // it is shown the `%` each GS separator is written as
+    let data = if gs1 && data.contains(&0x1d) {
+        Cow::Owned(data.iter().map(|&byte| if byte == 0x1d { b'%' } else { byte }).collect())
+    } else {
+        Cow::Borrowed(data)
+    };
+    let segments = Parser::new(&data).optimize(version);
+    if allow_kanji {
+        return segments.collect();
+    }
+
+    let mut merged: Vec<Segment> = Vec::new();
+    for mut segment in segments {
+        if segment.mode == Mode::Kanji {
+            segment.mode = Mode::Byte;
+        }
+        match merged.last_mut() {
+            Some(last) if last.mode == Mode::Byte && segment.mode == Mode::Byte => last.end = segment.end,
+            _ => merged.push(segment),
+        }
+    }
+    merged
+}
+
+/// The characters an Alphanumeric segment holding `part` writes. After
+/// FNC1 in first position a `%` stands for the GS separator, so the separator
+/// is written as one and a literal one is doubled.
+pub fn alphanumeric_characters(part: &[u8], gs1: bool) -> Cow<'_, [u8]> {
+    if !gs1 || !part.iter().any(|&byte| byte == b'%' || byte == 0x1d) {
+        return Cow::Borrowed(part);
+    }
+    Cow::Owned(part.iter().flat_map(|byte| match byte {
+        b'%' => &b"%%"[..],
+        0x1d => &b"%"[..],
+        _ => std::slice::from_ref(byte),
+    }).copied().collect())
+}
+
+/// Bits `segment` of `data` takes up in `version`, counting the doubled `%`
+/// of Alphanumeric segments in GS1 symbols.
+pub fn encoded_len(data: &[u8], segment: &Segment, version: Version, gs1: bool) -> usize {
+    if segment.mode != Mode::Alphanumeric {
+        return segment.encoded_len(version);
+    }
+    let length = segment.end - segment.begin;
+    let escaped = alphanumeric_characters(&data[segment.begin..segment.end], gs1).len();
+    // pairs of characters take 11 bits, a leftover one 6
+    let characters = |count: usize| count / 2 * 11 + count % 2 * 6;
+    segment.encoded_len(version) + characters(escaped) - characters(length)
+}
+
+/// Checks that manual segments cover `data` end to end and that each mode can
+/// hold its part of the data.
+pub fn validate_segments(data: &[u8], segments: &[Segment], gs1: bool) -> Result<(), GenerateError> {
+    let mut position = 0;
+    for (index, segment) in segments.iter().enumerate() {
+        if segment.begin != position || segment.end < segment.begin || segment.end > data.len() {
+            return Err(GenerateError::InvalidSegmentation);
+        }
+        if !mode_accepts(segment.mode, &data[segment.begin..segment.end], gs1) {
+            return Err(GenerateError::UnsupportedSegmentMode { index, mode: segment.mode });
+        }
+        position = segment.end;
+    }
+
+    if position == data.len() {
+        Ok(())
+    } else {
+        Err(GenerateError::InvalidSegmentation)
+    }
+}
@@ -0,0 +1,181 @@
+//! Stress testing a QR code before it is printed. The rendered symbol is
+//! blurred, made noisy, skewed, faded and covered in growing steps and read
+//! back after each one, to find how much of each its colors and error
+//! correction level survive.
+
+use std::fmt;
+
+use image::{imageops, GrayImage, Luma, Pixel, Rgb};
+
+use super::decode::{Perspective, Point, VerifyError};
+use super::fountain::Rng;
+use super::GeneratedQr;
+
+/// Steps of severity each degradation is tried at, mildest first.
+pub const LEVELS: usize = 10;
+
+// blur standard deviation, in tenths of a module, added at each level
+const BLUR_STEP_TENTHS: usize = 1;
+// standard deviation of the noise, in gray levels out of 255, added at each level
+const NOISE_STEP: usize = 12;
+// how much narrower the top edge gets at each level, as a percentage
+const SKEW_STEP_PERCENT: usize = 4;
+// contrast lost at each level, as a percentage
+const CONTRAST_STEP_PERCENT: usize = 9;
+// share of the symbol covered at each level, as a percentage
+const OCCLUSION_STEP_PERCENT: usize = 4;
+
+/// A kind of damage a printed code picks up before it is scanned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Degradation {
+    /// An out-of-focus camera or ink bleeding into the paper.
+    Blur,
+    /// Sensor noise and paper texture.
+    Noise,
+    /// Scanning at an angle, so the far edge looks narrower.
+    Skew,
+    /// Faded ink, or colors a printer cannot reproduce.
+    LowContrast,
+    /// A sticker, smudge or logo over the middle of the symbol.
+    Occlusion,
+}
+
+impl Degradation {
+    pub const ALL: [Degradation; 5] = [
+        Degradation::Blur,
+        Degradation::Noise,
+        Degradation::Skew,
+        Degradation::LowContrast,
+        Degradation::Occlusion,
+    ];
+
+    /// What `level`, from 1 to `LEVELS`, does to the symbol.
+    pub fn describe(self, level: usize) -> String {
+        match self {
+            Degradation::Blur => {
+                let tenths = level * BLUR_STEP_TENTHS;
+                format!("blurred by {}.{} modules", tenths / 10, tenths % 10)
+            },
+            Degradation::Noise => format!("noise of {} gray levels", level * NOISE_STEP),
+            Degradation::Skew => format!("{}% narrower at the top", level * SKEW_STEP_PERCENT),
+            Degradation::LowContrast => format!("{}% of the contrast", 100 - level * CONTRAST_STEP_PERCENT),
+            Degradation::Occlusion => format!("{}% covered", level * OCCLUSION_STEP_PERCENT),
+        }
+    }
+
+    // `image` after this degradation at `level`. `paper` is the background
+    // color and `symbol` the symbol's (offset, side) in pixels, inside the
+    // quiet zone.
+    fn apply(self, image: &GrayImage, level: usize, module_size: u32, paper: Luma<u8>, symbol: (u32, u32)) -> GrayImage {
+        match self {
+            Degradation::Blur => {
+                let sigma = (level * BLUR_STEP_TENTHS) as f32 / 10.0 * module_size as f32;
+                imageops::blur(image, sigma)
+            },
+            Degradation::Noise => {
+                let deviation = (level * NOISE_STEP) as f64;
+                // the same noise on every run, so a report can be reproduced
+                let mut rng = Rng::new(0, level as u32);
+                let mut uniform = || (rng.next() >> 11) as f64 / (1u64 << 53) as f64;
+                let mut noisy = image.clone();
+                for pixel in noisy.pixels_mut() {
+                    // Box-Muller transform of two uniform samples
+                    let gaussian = (-2.0 * (1.0 - uniform()).ln()).sqrt() * (std::f64::consts::TAU * uniform()).cos();
+                    pixel.0[0] = (pixel.0[0] as f64 + gaussian * deviation).round().clamp(0.0, 255.0) as u8;
+                }
+                noisy
+            },
+            Degradation::Skew => {
+                let (width, height) = (image.width() as f64, image.height() as f64);
+                let inset = width * (level * SKEW_STEP_PERCENT) as f64 / 200.0;
+                let corner = |x, y| Point { x, y };
+                // maps each pixel of the skewed image back to the one it shows
+                let Some(transform) = Perspective::from_points([
+                    (corner(inset, 0.0), corner(0.0, 0.0)),
+                    (corner(width - inset, 0.0), corner(width, 0.0)),
+                    (corner(0.0, height), corner(0.0, height)),
+                    (corner(width, height), corner(width, height)),
+                ]) else {
+                    return image.clone();
+                };
+                GrayImage::from_fn(image.width(), image.height(), |x, y| {
+                    let source = transform.map(corner(x as f64 + 0.5, y as f64 + 0.5));
+                    let inside = source.x >= 0.0 && source.y >= 0.0 && source.x < width && source.y < height;
+                    if inside { *image.get_pixel(source.x as u32, source.y as u32) } else { paper }
+                })
+            },
+            Degradation::LowContrast => {
+                let contrast = (100 - level * CONTRAST_STEP_PERCENT) as f64 / 100.0;
+                let paper = paper.0[0] as f64;
+                let mut faded = image.clone();
+                for pixel in faded.pixels_mut() {
+                    pixel.0[0] = (paper + (pixel.0[0] as f64 - paper) * contrast).round() as u8;
+                }
+                faded
+            },
+            Degradation::Occlusion => {
+                let (offset, side) = symbol;
+                let covered = (side as f64 * ((level * OCCLUSION_STEP_PERCENT) as f64 / 100.0).sqrt()) as u32;
+                let start = offset + (side - covered) / 2;
+                let mut covered_image = image.clone();
+                for y in start..start + covered {
+                    for x in start..start + covered {
+                        covered_image.put_pixel(x, y, paper);
+                    }
+                }
+                covered_image
+            },
+        }
+    }
+}
+
+impl fmt::Display for Degradation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Degradation::Blur => "Blur",
+            Degradation::Noise => "Noise",
+            Degradation::Skew => "Perspective skew",
+            Degradation::LowContrast => "Low contrast",
+            Degradation::Occlusion => "Occlusion",
+        })
+    }
+}
+
+/// How far a code survived one kind of degradation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StressResult {
+    pub degradation: Degradation,
+    /// The first level the code no longer read back at, and why, or `None`
+    /// if it survived every level.
+    pub failure: Option<(usize, VerifyError)>,
+}
+
+impl StressResult {
+    /// The most severe level the code still read back at, 0 for none.
+    pub fn passed(&self) -> usize {
+        self.failure.map_or(LEVELS, |(level, _)| level - 1)
+    }
+}
+
+impl GeneratedQr {
+    /// Renders the symbol with `module_size` pixels per module, in its own
+    /// colors, and reads it back after each degradation at every level
+    /// until one fails.
+    pub fn stress_test(&self, module_size: u32) -> Result<Vec<StressResult>, VerifyError> {
+        if !self.options.symbology.is_qr() {
+            return Err(VerifyError::NotSupported(self.options.symbology));
+        }
+        let image = image::DynamicImage::ImageRgb8(self.to_image(module_size)).to_luma8();
+        let (r, g, b) = self.options.background_color.to_rgb_u8();
+        let paper = Rgb([r, g, b]).to_luma();
+        let symbol = (self.quiet_zone() as u32 * module_size, self.width as u32 * module_size);
+
+        Ok(Degradation::ALL.into_iter().map(|degradation| {
+            let failure = (1..=LEVELS).find_map(|level| {
+                let degraded = degradation.apply(&image, level, module_size, paper, symbol);
+                self.verify_image(&degraded).err().map(|e| (level, e))
+            });
+            StressResult { degradation, failure }
+        }).collect())
+    }
+}
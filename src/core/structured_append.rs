@@ -0,0 +1,45 @@
+//! Structured Append: a payload too large for one symbol is split across up
+//! to 16 linked symbols that a supporting scanner reassembles.
+
+use qrcode::bits::{Bits, ExtendedMode};
+use qrcode::types::QrError;
+
+/// Most symbols a Structured Append sequence can have.
+pub const MAX_SYMBOLS: usize = 16;
+
+// mode indicator, position, total and parity
+pub(super) const HEADER_BITS: usize = 20;
+
+/// Where a symbol sits in its Structured Append sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StructuredAppend {
+    /// Zero-based position of the symbol.
+    pub index: u8,
+    /// Number of symbols in the sequence.
+    pub total: u8,
+    /// XOR of every byte of the whole payload, identical in all symbols.
+    pub parity: u8,
+}
+
+impl StructuredAppend {
+    pub fn parity(payload: &[u8]) -> u8 {
+        payload.iter().fold(0, |parity, byte| parity ^ byte)
+    }
+
+    // `Bits` has no way to push arbitrary numbers, so the 20 bit header is
+    // reserved with the mode indicator and four 4 bit placeholders, then
+    // overwritten by `write_header` once the bits are turned into bytes
+    pub(super) fn push_placeholder(bits: &mut Bits) -> Result<(), QrError> {
+        bits.push_mode_indicator(ExtendedMode::StructuredAppend)?;
+        for _ in 0..4 {
+            bits.push_mode_indicator(ExtendedMode::Fnc1First)?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn write_header(self, bytes: &mut [u8]) {
+        bytes[0] = 0b0011_0000 | self.index;
+        bytes[1] = ((self.total - 1) << 4) | (self.parity >> 4);
+        bytes[2] = (self.parity << 4) | (bytes[2] & 0x0f);
+    }
+}
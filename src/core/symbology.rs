@@ -0,0 +1,140 @@
+use std::fmt;
+
+use qrcode::types::Version;
+
+/// The family of codes to generate.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Symbology {
+    #[default]
+    Qr,
+    MicroQr,
+    DataMatrix,
+    Aztec,
+    /// Has no FNC1 codeword, so it holds no GS1 element strings; GS1 data
+    /// can still go in as a Digital Link URI.
+    Pdf417,
+    Rmqr,
+    Code128,
+    Ean13,
+    UpcA,
+}
+
+impl Symbology {
+    pub const ALL: [Symbology; 9] = [
+        Symbology::Qr,
+        Symbology::MicroQr,
+        Symbology::Rmqr,
+        Symbology::DataMatrix,
+        Symbology::Aztec,
+        Symbology::Pdf417,
+        Symbology::Code128,
+        Symbology::Ean13,
+        Symbology::UpcA,
+    ];
+
+    /// QR and Micro QR, the symbologies with versions, EC levels L to H,
+    /// masks and segments.
+    pub fn is_qr(self) -> bool {
+        matches!(self, Symbology::Qr | Symbology::MicroQr)
+    }
+
+    /// QR, Micro QR and rMQR, which share the encoding modes and the error
+    /// correction levels (rMQR only has M and H).
+    pub fn is_qr_family(self) -> bool {
+        self.is_qr() || self == Symbology::Rmqr
+    }
+
+    /// The one-dimensional barcodes, drawn as bars with optional text below.
+    pub fn is_linear(self) -> bool {
+        matches!(self, Symbology::Code128 | Symbology::Ean13 | Symbology::UpcA)
+    }
+
+    /// Modules of blank margin the symbology asks for around the symbol.
+    pub fn quiet_zone(self) -> usize {
+        match self {
+            Symbology::Qr => 4,
+            Symbology::MicroQr | Symbology::Rmqr | Symbology::Pdf417 => 2,
+            Symbology::DataMatrix => 1,
+            // the bull's-eye is found without one
+            Symbology::Aztec => 0,
+            Symbology::Ean13 => 11,
+            Symbology::UpcA => 9,
+            Symbology::Code128 => 10,
+        }
+    }
+
+    pub fn min_version(self) -> i16 {
+        1
+    }
+
+    /// Symbologies that are not QR have a single placeholder version.
+    pub fn max_version(self) -> i16 {
+        match self {
+            Symbology::Qr => 40,
+            Symbology::MicroQr => 4,
+            _ => 1,
+        }
+    }
+
+    /// The `qrcode` version for a version number of this symbology.
+    pub fn version(self, number: i16) -> Version {
+        match self {
+            Symbology::MicroQr => Version::Micro(number),
+            _ => Version::Normal(number),
+        }
+    }
+
+    /// How a version number is written for this symbology, e.g. `5` or `M2`.
+    pub fn version_name(self, number: i16) -> String {
+        match self {
+            Symbology::MicroQr => format!("M{number}"),
+            _ => number.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Symbology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Symbology::Qr => "QR code",
+            Symbology::MicroQr => "Micro QR code",
+            Symbology::DataMatrix => "Data Matrix",
+            Symbology::Aztec => "Aztec code",
+            Symbology::Pdf417 => "PDF417",
+            Symbology::Rmqr => "rMQR code",
+            Symbology::Code128 => "Code 128",
+            Symbology::Ean13 => "EAN-13",
+            Symbology::UpcA => "UPC-A",
+        })
+    }
+}
+
+/// The size of a generated symbol, in the terms of its symbology.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolSize {
+    Qr(Version),
+    DataMatrix { rows: usize, columns: usize },
+    Aztec { compact: bool, layers: usize },
+    Pdf417 { rows: usize, columns: usize, security_level: u8 },
+    Rmqr { height: usize, width: usize },
+    Linear { symbology: Symbology, modules: usize },
+}
+
+impl fmt::Display for SymbolSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolSize::Qr(Version::Normal(number)) => write!(f, "version {number}"),
+            SymbolSize::Qr(Version::Micro(number)) => write!(f, "version M{number}"),
+            SymbolSize::DataMatrix { rows, columns } => write!(f, "{rows}×{columns}"),
+            SymbolSize::Aztec { compact, layers } => {
+                let format = if *compact { "compact" } else { "full-range" };
+                write!(f, "{format}, {layers} layer{}", if *layers == 1 { "" } else { "s" })
+            },
+            SymbolSize::Pdf417 { rows, columns, security_level } => {
+                write!(f, "{rows} rows of {columns} column{}, security level {security_level}", if *columns == 1 { "" } else { "s" })
+            },
+            SymbolSize::Rmqr { height, width } => write!(f, "R{height}×{width}"),
+            SymbolSize::Linear { modules, .. } => write!(f, "{modules} modules wide"),
+        }
+    }
+}